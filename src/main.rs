@@ -3,16 +3,23 @@
 // ==============================================
 
 use anyhow::{anyhow, Context, Result};
-use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveTime, TimeZone};
 use clap::{Parser, Subcommand, ValueEnum};
-use dialoguer::{theme::ColorfulTheme, Select};
+use console::Style;
+use dialoguer::{theme::ColorfulTheme, Confirm, Input, Select};
 use dirs::home_dir;
+use indexmap::IndexMap;
+use regex::{Regex, RegexBuilder};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
 use std::fs::{self, File, OpenOptions};
-use std::io::{self, BufRead, BufReader, Write};
-use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::io::{self, BufRead, BufReader, IsTerminal, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+use std::process::{Command, Stdio};
+use walkdir::WalkDir;
 
 // ==============================================
 // CONSTANTS & CONFIG
@@ -23,34 +30,628 @@ const CONFIG_FILE: &str = "config.toml";
 const CURRENT_AREA_FILE: &str = "current_area";
 const SESSIONS_FILE: &str = "sessions.csv";
 const SESSION_START_FILE: &str = "session_start";
+const PROCESSES_FILE: &str = "processes.toml";
+const LOCK_FILE: &str = ".lock";
+const BACKUP_DIR: &str = ".backup";
+const TRASH_DIR: &str = ".trash";
+const TRASH_MANIFEST: &str = "manifest.toml";
+const SNAPSHOTS_DIR: &str = ".snapshots";
+const RESTORE_STAGING_DIR: &str = ".restore";
+const BACKUP_MANIFEST: &str = "manifest.toml";
+const INDEX_DIR: &str = ".index";
+const INDEX_FILE: &str = "files.json";
+// Bump to make every install rebuild its search index
+const INDEX_VERSION: u32 = 1;
+
+/// Top-level entries of the data dir that are iceland's own bookkeeping and stay out of backups.
+/// `processes.toml` is left out too: its PIDs mean nothing after a restore,
+/// and the search index is rebuilt from the files on the next search.
+/// A running timer is left out so a restore never brings back an old one.
+const BACKUP_EXCLUDE: &[&str] = &[
+    LOCK_FILE,
+    SESSION_START_FILE,
+    BACKUP_DIR,
+    TRASH_DIR,
+    SNAPSHOTS_DIR,
+    PROFILES_DIR,
+    RESTORE_STAGING_DIR,
+    PROCESSES_FILE,
+    INDEX_DIR,
+];
+const PROFILES_DIR: &str = ".profiles";
+const TEMPLATES_DIR: &str = "templates";
+const TEMPLATE_MANIFEST: &str = "template.toml";
+const DEFAULT_TEMPLATE: &str = "basic";
+
+// Version of the on-disk layout, stored in config.toml; bump it when adding a migration
+const SCHEMA_VERSION: u32 = 4;
+
+// Column order written by `Session`; older files are upgraded on the next write
+const SESSIONS_HEADER: &str = "area,start,end,tag,note,planned,id";
+
+// Notes are `<area>/notes/<id>.md`; this is the single file used before that
+const LEGACY_NOTES_FILE: &str = "my_notes.txt";
+
+// Journal entries are `<area>/notes/journal/YYYY-MM-DD.md`; the session summary
+// sits between these markers and is rewritten each time the day is opened
+const JOURNAL_DIR: &str = "journal";
+const JOURNAL_SUMMARY_START: &str = "<!-- iceland:sessions -->";
+const JOURNAL_SUMMARY_END: &str = "<!-- /iceland:sessions -->";
+
+// Where `capture` puts notes when no area is current; `inbox triage` empties it
+const INBOX_AREA: &str = "inbox";
+
+// Pomodoro defaults
+const POMODORO_TAG: &str = "pomodoro";
+
+// Spaced repetition (SM-2) parameters
+const FLASHCARD_STATE_SUFFIX: &str = ".srs";
+const DEFAULT_EASE: f64 = 2.5;
+const MIN_EASE: f64 = 1.3;
+
+// Browser profile seeds, written into a new or reset profile. A file of the same
+// name in templates/ replaces the built-in one; `{area}` is replaced with the area name.
+const FIREFOX_SEED_FILE: &str = "firefox-user.js";
+const CHROMIUM_SEED_FILE: &str = "chromium-preferences.json";
+const FIREFOX_USER_JS: &str = r#"// Written by iceland for the '{area}' area
+user_pref("browser.shell.checkDefaultBrowser", false);
+user_pref("browser.aboutwelcome.enabled", false);
+user_pref("browser.startup.homepage_override.mstone", "ignore");
+user_pref("datareporting.policy.dataSubmissionPolicyBypassNotification", true);
+user_pref("toolkit.telemetry.reportingpolicy.firstRun", false);
+"#;
+const CHROMIUM_PREFERENCES: &str = r#"{"browser":{"check_default_browser":false},"profile":{"name":"{area}"}}"#;
+
+// What `browser_command` defaulted to before iceland managed profiles itself
+const LEGACY_BROWSER_COMMAND: &str = "firefox -P {area}";
+
+// Built-in area templates, used when no user template of the same name exists.
+// `{area}` in links and files is replaced with the area name.
+const BUILTIN_TEMPLATES: &[(&str, &str)] = &[
+    (
+        "basic",
+        r##"
+description = "Notes, flashcards and an empty links file"
+links = ["# Links for {area}", ""]
+"##,
+    ),
+    (
+        "math",
+        r##"
+description = "Math area with a Firefox profile and Khan Academy"
+links = ["Math resources:", "https://www.khanacademy.org"]
+"##,
+    ),
+    (
+        "learning",
+        r##"
+description = "Study area with a Comet profile and university links"
+links = [
+    "Primuss: https://www3.primuss.de/",
+    "Wikipedia: https://www.wikipedia.org",
+    "ChatGPT: https://chat.openai.com",
+]
+
+[area]
+browser = "comet"
+"##,
+    ),
+    (
+        "work",
+        r##"
+description = "Projects, docs and a separate browser profile"
+directories = ["projects", "docs"]
+"##,
+    ),
+    (
+        "gaming",
+        r##"
+description = "Games, clips and a separate browser profile"
+directories = ["games", "clips"]
+"##,
+    ),
+    (
+        "inbox",
+        r##"
+description = "Quick captures waiting to be sorted with `iceland inbox triage`"
+"##,
+    ),
+    (
+        "traveling",
+        r##"
+description = "Travel plans"
+directories = ["plans"]
+"##,
+    ),
+    (
+        "trading",
+        r##"
+description = "Market analysis"
+directories = ["analysis"]
+"##,
+    ),
+];
 
 // Default configuration (used when no config exists)
 const DEFAULT_AREAS: &[&str] = &["work", "math", "learning", "gaming", "traveling", "trading"];
+const DEFAULT_MAX_SESSION: &str = "8h";
+const DEFAULT_HOOK_TIMEOUT: &str = "10s";
 
 // ==============================================
 // DATA STRUCTURES
 // ==============================================
 
+/// Missing fields fall back to `Config::default()`, so older files keep loading.
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
 struct Config {
-    areas: Vec<String>,
-    browser_command: String, // e.g., "firefox -P {area}"
+    #[serde(default)]
+    schema_version: u32, // 0 for files written before versioning
+    browser: Browser, // managed per-area profile, used when browser_command is empty
+    browser_command: String, // custom launch command instead, e.g. "firefox -P {area}"
+    max_session: String, // sessions longer than this were probably forgotten, e.g. "8h"
+    snapshots: usize, // backups kept in .snapshots, taken before destroy/remove-area/restore; 0 = off
+    close_apps: CloseApps, // what happens to an area's launched apps when switching away
+    close_grace: String, // how long apps get to quit before being killed, e.g. "5s"
+    #[serde(skip_serializing_if = "AreaHooks::is_empty")]
+    hooks: AreaHooks, // run for every area, before the area's own hooks
+    #[serde(deserialize_with = "deserialize_areas")]
+    areas: IndexMap<String, AreaConfig>, // [areas.<name>] tables, in display order
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
-            areas: DEFAULT_AREAS.iter().map(|s| s.to_string()).collect(),
-            browser_command: "firefox -P {area}".to_string(),
+            schema_version: SCHEMA_VERSION,
+            browser: Browser::Firefox,
+            browser_command: String::new(),
+            max_session: DEFAULT_MAX_SESSION.to_string(),
+            snapshots: 0,
+            close_apps: CloseApps::Keep,
+            close_grace: "5s".to_string(),
+            hooks: AreaHooks::default(),
+            areas: DEFAULT_AREAS
+                .iter()
+                .map(|s| (s.to_string(), AreaConfig::default()))
+                .collect(),
         }
     }
 }
 
+/// Per-area settings from an `[areas.<name>]` table.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct AreaConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    icon: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    color: Option<String>, // console style, e.g. "cyan" or "green.bold"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    browser: Option<Browser>, // overrides the global browser
+    #[serde(skip_serializing_if = "Option::is_none")]
+    browser_command: Option<String>, // overrides everything above
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    directories: Vec<String>, // extra directories created inside the area
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    links: Vec<String>, // shown on switch, before links.txt
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    launch: Vec<LaunchAction>, // started on switch, after the browser
+    #[serde(skip_serializing_if = "Option::is_none")]
+    close_apps: Option<CloseApps>, // overrides the global close_apps
+    #[serde(skip_serializing_if = "AreaHooks::is_empty")]
+    hooks: AreaHooks,
+    #[serde(skip_serializing_if = "AreaGoals::is_empty")]
+    goals: AreaGoals,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    archived: bool, // hidden from list/tui, data and history kept
+}
+
+/// A program started on `switch`, from an `[[areas.<name>.launch]]` table.
+///
+/// `command` uses shell-style quoting; `cwd` is a plain path. Both may
+/// contain `{area}`, `{area_dir}`, `{notes_dir}` and `{date}`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct LaunchAction {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>, // shown instead of the command
+    command: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cwd: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    wait: bool, // run in the foreground instead of detaching
+}
+
+/// Layout for a new area: a `templates/<name>.toml` manifest, a
+/// `templates/<name>/` directory (copied as-is, with an optional
+/// `template.toml` manifest inside) or one of `BUILTIN_TEMPLATES`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Template {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    directories: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    links: Vec<String>, // lines of links.txt
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    files: IndexMap<String, String>, // relative path -> content
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    flashcards: IndexMap<String, Vec<String>>, // deck file -> "front|back" lines
+    #[serde(skip_serializing_if = "AreaConfig::is_empty")]
+    area: AreaConfig, // copied into [areas.<name>]
+    #[serde(skip)]
+    source_dir: Option<PathBuf>,
+}
+
+/// Shell commands run on area lifecycle events, globally (`[hooks]`) or per area.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct AreaHooks {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    on_enter: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    on_leave: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    on_session_start: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    on_session_stop: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    on_note_added: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timeout: Option<String>, // hooks running longer are killed; default from [hooks], else 10s
+    #[serde(skip_serializing_if = "Option::is_none")]
+    on_failure: Option<HookFailure>, // default from [hooks], else warn
+}
+
+/// What a failing or timed-out hook does to the command that ran it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum HookFailure {
+    #[default]
+    Warn,
+    Abort,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum HookEvent {
+    Enter,
+    Leave,
+    SessionStart,
+    SessionStop,
+    NoteAdded,
+}
+
+impl AreaConfig {
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl AreaHooks {
+    fn is_empty(&self) -> bool {
+        self.on_enter.is_none()
+            && self.on_leave.is_none()
+            && self.on_session_start.is_none()
+            && self.on_session_stop.is_none()
+            && self.on_note_added.is_none()
+            && self.timeout.is_none()
+            && self.on_failure.is_none()
+    }
+}
+
+/// A note stored as `<area>/notes/<id>.md` with YAML front-matter.
+#[derive(Debug, Clone)]
+struct Note {
+    id: String,
+    title: Option<String>,
+    area: String, // the directory it lives in wins over the front-matter
+    tags: Vec<String>,
+    created: DateTime<Local>,
+    updated: DateTime<Local>,
+    body: String,
+    path: PathBuf,
+}
+
+/// What `search` looks through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+enum SearchScope {
+    Notes,
+    Links,
+    Flashcards,
+}
+
+/// `.index/files.json`: the searchable lines of every file, keyed by path
+/// relative to the data dir. Entries are refreshed when size or mtime change.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SearchIndex {
+    version: u32,
+    files: IndexMap<String, IndexedFile>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct IndexedFile {
+    area: String,
+    scope: SearchScope,
+    modified: u64, // nanoseconds since the epoch
+    size: u64,
+    lines: Vec<IndexedLine>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
+struct IndexedLine {
+    line: usize,
+    text: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    heading: bool, // note title or Markdown heading, ranked higher
+}
+
+/// A browser whose profile iceland creates and owns at `<area_dir>/<profile_dir>`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Browser {
+    #[default]
+    Firefox,
+    Chromium,
+    Chrome,
+    Brave,
+    Comet,
+    #[serde(rename = "none")]
+    Disabled,
+}
+
+/// Whether `switch` closes the apps launched for the area being left.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum CloseApps {
+    Close,
+    #[default]
+    Keep,
+    Ask,
+}
+
+/// A detached process started by a launch action, recorded in `processes.toml`.
+/// It runs in its own process group, so `pid` is also the group id.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LaunchedProcess {
+    area: String,
+    pid: u32,
+    program: String,
+    started: DateTime<Local>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    start_ticks: Option<u64>, // from /proc, to tell our process from one that reused the PID
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LaunchedProcesses {
+    #[serde(default, rename = "process")]
+    processes: Vec<LaunchedProcess>,
+}
+
+/// Time targets for an area, as durations like "2h".
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct AreaGoals {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    daily: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    weekly: Option<String>,
+}
+
+impl AreaGoals {
+    fn is_empty(&self) -> bool {
+        self.daily.is_none() && self.weekly.is_none()
+    }
+}
+
+/// Accept both the old flat `areas = ["work", ...]` list and `[areas.<name>]` tables.
+fn deserialize_areas<'de, D>(deserializer: D) -> std::result::Result<IndexMap<String, AreaConfig>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Areas {
+        List(Vec<String>),
+        Tables(IndexMap<String, AreaConfig>),
+    }
+
+    Ok(match Areas::deserialize(deserializer)? {
+        Areas::List(names) => names.into_iter().map(|n| (n, AreaConfig::default())).collect(),
+        Areas::Tables(tables) => tables,
+    })
+}
+
+impl Config {
+    fn area(&self, name: &str) -> AreaConfig {
+        self.areas.get(name).cloned().unwrap_or_default()
+    }
+
+    fn max_session_length(&self) -> Duration {
+        parse_duration(&self.max_session).unwrap_or_else(|e| {
+            eprintln!("Warning: invalid max_session in config ({}), using {}", e, DEFAULT_MAX_SESSION);
+            Duration::hours(8)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct Session {
     area: String,
     start: DateTime<Local>,
     end: DateTime<Local>,
+    #[serde(default)]
+    tag: Option<String>,
+    #[serde(default)]
+    note: Option<String>,
+    #[serde(default)]
+    planned: Option<i64>, // seconds, for timeboxed sessions
+    #[serde(default)]
+    id: u64, // 0 until assigned
+}
+
+/// The timer currently running, stored in `session_start`.
+#[derive(Debug, Serialize, Deserialize)]
+struct RunningSession {
+    area: String,
+    start: DateTime<Local>,
+    tag: Option<String>,
+    note: Option<String>,
+    planned: Option<i64>,
+}
+
+impl RunningSession {
+    fn new(area: &str) -> Self {
+        Self {
+            area: area.to_string(),
+            start: Local::now(),
+            tag: None,
+            note: None,
+            planned: None,
+        }
+    }
+
+    /// When a timeboxed session closes itself.
+    fn deadline(&self) -> Option<DateTime<Local>> {
+        self.planned.map(|secs| self.start + Duration::seconds(secs))
+    }
+
+    fn finish(self, end: DateTime<Local>) -> Session {
+        Session {
+            area: self.area,
+            start: self.start,
+            end,
+            tag: self.tag,
+            note: self.note,
+            planned: self.planned,
+            id: 0,
+        }
+    }
+}
+
+/// Review state of a single flashcard, keyed by its front text.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CardState {
+    front: String,
+    ease: f64,
+    interval: i64, // days until the next review
+    repetitions: u32,
+    due: NaiveDate,
+}
+
+impl CardState {
+    fn new(front: &str, today: NaiveDate) -> Self {
+        Self {
+            front: front.to_string(),
+            ease: DEFAULT_EASE,
+            interval: 0,
+            repetitions: 0,
+            due: today,
+        }
+    }
+
+    /// Apply an SM-2 review step for the given grade.
+    fn review(&mut self, grade: Grade, today: NaiveDate) {
+        let q = grade.quality() as f64;
+        if q < 3.0 {
+            self.repetitions = 0;
+            self.interval = 1;
+        } else {
+            self.interval = match self.repetitions {
+                0 => 1,
+                1 => 6,
+                _ => (self.interval as f64 * self.ease).round() as i64,
+            };
+            self.repetitions += 1;
+        }
+        self.ease = (self.ease + 0.1 - (5.0 - q) * (0.08 + (5.0 - q) * 0.02)).max(MIN_EASE);
+        self.due = today + Duration::days(self.interval);
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Grade {
+    Again,
+    Hard,
+    Good,
+    Easy,
+}
+
+impl Grade {
+    const ALL: [Grade; 4] = [Grade::Again, Grade::Hard, Grade::Good, Grade::Easy];
+
+    fn label(self) -> &'static str {
+        match self {
+            Grade::Again => "Again",
+            Grade::Hard => "Hard",
+            Grade::Good => "Good",
+            Grade::Easy => "Easy",
+        }
+    }
+
+    /// SM-2 response quality (0-5)
+    fn quality(self) -> u8 {
+        match self {
+            Grade::Again => 1,
+            Grade::Hard => 3,
+            Grade::Good => 4,
+            Grade::Easy => 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum StatsGroup {
+    Day,
+    Week,
+    Month,
+    Area,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    Table,
+    Json,
+    Csv,
+}
+
+/// What was moved into one `.trash/<id>/` entry and where it came from.
+#[derive(Debug, Serialize, Deserialize)]
+struct TrashManifest {
+    created: DateTime<Local>,
+    description: String,
+    items: Vec<PathBuf>, // relative to the iceland dir, stored under the same path in the entry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    area: Option<String>, // set when a whole area was removed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    area_config: Option<AreaConfig>,
+}
+
+/// Written first into every backup archive. `files` maps archive paths
+/// (`config.toml`, `data/...`) to their SHA-256.
+#[derive(Debug, Serialize, Deserialize)]
+struct BackupManifest {
+    schema_version: u32,
+    app_version: String,
+    created: DateTime<Local>,
+    areas: Vec<String>,
+    files: IndexMap<String, String>,
+}
+
+/// Something `iceland doctor` found out of place.
+#[derive(Debug)]
+enum Problem {
+    MissingDir(String),
+    OrphanDir(String),
+    DanglingCurrentArea(String),
+    StaleRunningSession(String), // why it is stale
+    UnparseableSession { line: u64, error: String },
+    NegativeSession(u64),
+    OverlappingSessions(u64, u64),
+    MalformedCards { deck: PathBuf, lines: Vec<usize> },
 }
 
 #[derive(Debug, Clone, ValueEnum)]
@@ -66,6 +667,14 @@ enum DestroyTarget {
 #[derive(Parser)]
 #[command(name = APP_NAME, version, about = "Manage focused digital areas with tools, links, notes, and time tracking", long_about = None)]
 struct Cli {
+    /// Data directory to use instead of ~/.iceland
+    #[arg(long, global = true, env = "ICELAND_HOME", value_name = "PATH")]
+    home: Option<PathBuf>,
+
+    /// Use a separate named profile (e.g. personal, job)
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -77,10 +686,19 @@ enum Commands {
     Init,
 
     /// List all available areas
-    List,
+    List {
+        /// Include archived areas
+        #[arg(long)]
+        all: bool,
+    },
 
     /// Switch to a specific area
-    Switch { area: String },
+    Switch {
+        area: String,
+        /// Don't start the area's browser or launch actions
+        #[arg(long)]
+        no_launch: bool,
+    },
 
     /// Interactive area selector (TUI)
     Tui,
@@ -89,52 +707,426 @@ enum Commands {
     Status,
 
     /// Show time statistics per area
-    Stats,
+    Stats {
+        /// Only include sessions starting on or after this date (YYYY-MM-DD)
+        #[arg(long, conflicts_with_all = ["today", "week", "month"])]
+        since: Option<NaiveDate>,
+        /// Only include sessions starting on or before this date (YYYY-MM-DD)
+        #[arg(long, conflicts_with_all = ["today", "week", "month"])]
+        until: Option<NaiveDate>,
+        /// Only today's sessions
+        #[arg(long, conflicts_with_all = ["week", "month"])]
+        today: bool,
+        /// Sessions since Monday of this week
+        #[arg(long, conflicts_with = "month")]
+        week: bool,
+        /// Sessions since the first of this month
+        #[arg(long)]
+        month: bool,
+        #[arg(long, value_enum, default_value_t = StatsGroup::Area)]
+        group_by: StatsGroup,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
 
     /// Manually start a session timer in the current area
-    Start,
+    Start {
+        #[arg(short, long)]
+        tag: Option<String>,
+        #[arg(short, long)]
+        note: Option<String>,
+        /// Stop automatically after this long (e.g. 25m, 1h30m)
+        #[arg(long = "for", value_parser = parse_duration)]
+        duration: Option<Duration>,
+    },
+
+    /// Run pomodoro work/break cycles in the current area
+    Pomodoro {
+        #[arg(long, value_parser = parse_duration, default_value = "25m")]
+        work: Duration,
+        #[arg(long, value_parser = parse_duration, default_value = "5m")]
+        short_break: Duration,
+        #[arg(long, value_parser = parse_duration, default_value = "15m")]
+        long_break: Duration,
+        /// Work blocks before a long break
+        #[arg(long, default_value_t = 4)]
+        cycles: u32,
+        /// Total work blocks to run
+        #[arg(long, default_value_t = 4)]
+        rounds: u32,
+    },
 
     /// Stop the current session and record it
-    Stop,
+    Stop {
+        /// Record the session as ending at this time (HH:MM or YYYY-MM-DD HH:MM)
+        #[arg(long, value_parser = parse_time, conflicts_with = "ago", allow_hyphen_values = true)]
+        at: Option<DateTime<Local>>,
+        /// Record the session as ending this long ago (e.g. 2h, 45m)
+        #[arg(long, value_parser = parse_duration)]
+        ago: Option<Duration>,
+    },
 
-    /// Destroy/reset a component in an area
+    /// Destroy/reset a component in an area (old content goes to the trash)
     Destroy {
         area: String,
         #[arg(value_enum)]
         target: DestroyTarget,
+        /// Delete immediately instead of moving to the trash
+        #[arg(long)]
+        hard: bool,
+    },
+
+    /// Add, list, show or edit notes (`notes <area> ["text"|-]` adds one)
+    #[command(args_conflicts_with_subcommands = true)]
+    Notes {
+        #[command(subcommand)]
+        action: Option<NotesAction>,
+        area: Option<String>,
+        text: Option<String>,
+    },
+
+    /// Open or append to an area's journal for a day (default: current area, today)
+    #[command(args_conflicts_with_subcommands = true)]
+    Journal {
+        #[command(subcommand)]
+        action: Option<JournalAction>,
+        area: Option<String>,
+        /// Append this instead of opening $EDITOR (`-` reads stdin)
+        text: Option<String>,
+        /// The day to open (today, yesterday or YYYY-MM-DD)
+        #[arg(long, value_parser = parse_date)]
+        date: Option<NaiveDate>,
+    },
+
+    /// Quickly note something in the current area (or the inbox)
+    Capture {
+        /// The text (no text opens $EDITOR, `-` reads stdin)
+        text: Option<String>,
+        /// Capture into this area instead of the current one
+        #[arg(short, long)]
+        area: Option<String>,
+        #[arg(short, long = "tag", value_delimiter = ',')]
+        tags: Vec<String>,
+    },
+
+    /// List inbox items, or sort them into areas
+    Inbox {
+        #[command(subcommand)]
+        action: Option<InboxAction>,
     },
 
-    /// Add a note to an area
-    Notes { area: String, text: String },
+    /// Search notes, links and flashcards
+    Search {
+        query: String,
+        #[arg(short, long)]
+        area: Option<String>,
+        /// Where to look (default: everywhere)
+        #[arg(long = "in", value_enum, value_delimiter = ',')]
+        scopes: Vec<SearchScope>,
+        /// Treat the query as a regular expression
+        #[arg(short, long)]
+        regex: bool,
+        /// Show at most this many results
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: usize,
+    },
 
     /// Study flashcards for an area
     Flashcards { area: String },
 
     /// Add a new custom area
-    AddArea { name: String },
+    AddArea {
+        name: String,
+        /// Template to create the area from (defaults to one named like the area, else "basic")
+        #[arg(short, long)]
+        template: Option<String>,
+    },
+
+    /// List, inspect and create area templates
+    Template {
+        #[command(subcommand)]
+        action: TemplateAction,
+    },
+
+    /// Remove an existing area (its data goes to the trash)
+    RemoveArea {
+        name: String,
+        /// Delete immediately instead of moving to the trash
+        #[arg(long)]
+        hard: bool,
+    },
+
+    /// Rename an area, its directory and its recorded sessions
+    RenameArea { old: String, new: String },
 
-    /// Remove an existing area (deletes all its data)
-    RemoveArea { name: String },
+    /// Create a new area with the same structure and settings as another
+    CloneArea {
+        src: String,
+        dst: String,
+        /// Also copy notes, flashcard progress and all other files
+        #[arg(long)]
+        with_content: bool,
+    },
+
+    /// Hide an area from list and tui, keeping its data and history
+    ArchiveArea { name: String },
+
+    /// Bring an archived area back
+    UnarchiveArea { name: String },
 
     /// Show session history for an area (or all areas)
     History {
         #[arg(short, long)]
         area: Option<String>,
     },
+
+    /// Record a session after the fact, e.g. for time away from the machine
+    Log {
+        area: String,
+        /// Start time (HH:MM, "yesterday 14:00", YYYY-MM-DD HH:MM or -2h)
+        #[arg(long, allow_hyphen_values = true)]
+        start: Option<String>,
+        /// End time, same formats as --start
+        #[arg(long, allow_hyphen_values = true)]
+        end: Option<String>,
+        /// Length of the session (e.g. 1h30m)
+        #[arg(long, value_parser = parse_duration)]
+        duration: Option<Duration>,
+        /// Day the session happened (today, yesterday or YYYY-MM-DD)
+        #[arg(long, value_parser = parse_date)]
+        date: Option<NaiveDate>,
+        #[arg(short, long)]
+        tag: Option<String>,
+        #[arg(short, long)]
+        note: Option<String>,
+    },
+
+    /// List, edit, split, merge or delete recorded sessions
+    Session {
+        #[command(subcommand)]
+        action: SessionAction,
+    },
+
+    /// List or purge deleted content
+    Trash {
+        #[command(subcommand)]
+        action: TrashAction,
+    },
+
+    /// Check config, area folders, sessions and decks for inconsistencies
+    Doctor {
+        /// Repair the problems found, asking how for each one
+        #[arg(long)]
+        fix: bool,
+    },
+
+    /// Write a backup archive of all areas, notes, decks and sessions
+    Backup {
+        /// Archive to write (defaults to iceland-<timestamp>.tar.zst in the current directory)
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+
+    /// Put a trash entry back, or restore a backup archive
+    Restore {
+        /// Trash id (see `trash list`) or path to a backup archive
+        target: String,
+        /// Only restore this area from the archive
+        #[arg(long)]
+        area: Option<String>,
+        /// Check the archive and show what would change without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum TrashAction {
+    /// List trash entries, newest first
+    List,
+
+    /// Permanently delete trash entries
+    Purge {
+        /// Only purge entries older than this (e.g. 30d, 12h); default is everything
+        #[arg(long, value_parser = parse_duration)]
+        older_than: Option<Duration>,
+    },
+}
+
+#[derive(Subcommand)]
+enum NotesAction {
+    /// Add a note to an area (no text opens $EDITOR, `-` reads stdin)
+    Add {
+        area: String,
+        text: Option<String>,
+        #[arg(long)]
+        title: Option<String>,
+        /// Tag the note (repeat or separate with commas)
+        #[arg(short, long = "tag", value_delimiter = ',')]
+        tags: Vec<String>,
+    },
+
+    /// List notes, oldest first
+    List {
+        #[arg(short, long)]
+        area: Option<String>,
+        /// Only notes with this tag
+        #[arg(long)]
+        tag: Option<String>,
+        /// Only notes created on or after this date (today, yesterday or YYYY-MM-DD)
+        #[arg(long, value_parser = parse_date)]
+        since: Option<NaiveDate>,
+    },
+
+    /// Print a note (`<id>` or `<area>/<id>`)
+    Show { id: String },
+
+    /// Open a note in $EDITOR
+    Edit { id: String },
+}
+
+#[derive(Subcommand)]
+enum InboxAction {
+    /// Walk through the inbox and move each item to an area's notes, links or flashcards
+    Triage,
+}
+
+#[derive(Subcommand)]
+enum JournalAction {
+    /// Show which days have journal entries, newest first
+    List {
+        #[arg(short, long)]
+        area: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum TemplateAction {
+    /// List user and built-in templates
+    List,
+
+    /// Show what a template creates
+    Show { name: String },
+
+    /// Save an existing area's layout as a new template
+    CreateFrom {
+        area: String,
+        /// Template name (defaults to the area name)
+        #[arg(long)]
+        name: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum SessionAction {
+    /// List recorded sessions
+    List {
+        /// Show session ids for use with edit/delete/split/merge
+        #[arg(long)]
+        ids: bool,
+        #[arg(short, long)]
+        area: Option<String>,
+    },
+
+    /// Change the start, end or area of a session (a bare HH:MM is on the session's own day)
+    Edit {
+        id: u64,
+        #[arg(long, allow_hyphen_values = true)]
+        start: Option<String>,
+        #[arg(long, allow_hyphen_values = true)]
+        end: Option<String>,
+        #[arg(long)]
+        area: Option<String>,
+    },
+
+    /// Delete a session
+    Delete { id: u64 },
+
+    /// Split a session in two at the given time (a bare HH:MM is on the session's own day)
+    Split {
+        id: u64,
+        #[arg(long, allow_hyphen_values = true)]
+        at: String,
+    },
+
+    /// Merge two sessions of the same area into one
+    Merge { first: u64, second: u64 },
 }
 
 // ==============================================
 // PATH HELPERS
 // ==============================================
 
+/// Where this invocation keeps its data and config, resolved once in `main`.
+struct DataPaths {
+    data: PathBuf,
+    config: PathBuf,
+    profile: Option<String>,
+}
+
+static DATA_PATHS: OnceLock<DataPaths> = OnceLock::new();
+
+/// Pick the data and config directories, in order of precedence:
+/// `--home`/`ICELAND_HOME`, an existing `~/.iceland`, `$XDG_DATA_HOME` and
+/// `$XDG_CONFIG_HOME` (either one is enough; the other falls back to its XDG
+/// default), and finally `~/.iceland` for new installs.
+/// A profile lives in `.profiles/<name>` below the chosen directories.
+fn resolve_data_paths(home: Option<PathBuf>, profile: Option<String>) -> Result<DataPaths> {
+    let legacy = home_dir().map(|h| h.join(format!(".{}", APP_NAME)));
+    let xdg_data = std::env::var_os("XDG_DATA_HOME").filter(|v| !v.is_empty());
+    let xdg_config = std::env::var_os("XDG_CONFIG_HOME").filter(|v| !v.is_empty());
+    let (data, config) = match (home, legacy) {
+        (Some(home), _) => (home.clone(), home),
+        (None, Some(legacy)) if legacy.is_dir() => (legacy.clone(), legacy),
+        (None, _) if xdg_data.is_some() || xdg_config.is_some() => {
+            let xdg_dir = |var: Option<std::ffi::OsString>, default: &str| {
+                var.map(PathBuf::from)
+                    .or_else(|| home_dir().map(|h| h.join(default)))
+                    .map(|base| base.join(APP_NAME))
+                    .ok_or_else(|| anyhow!("Could not find home directory; set ICELAND_HOME or pass --home"))
+            };
+            (xdg_dir(xdg_data, ".local/share")?, xdg_dir(xdg_config, ".config")?)
+        }
+        (None, legacy) => {
+            let legacy = legacy
+                .ok_or_else(|| anyhow!("Could not find home directory; set ICELAND_HOME or pass --home"))?;
+            (legacy.clone(), legacy)
+        }
+    };
+
+    match profile {
+        Some(name) => {
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+                return Err(anyhow!("Invalid profile name '{}' (use letters, digits, - and _)", name));
+            }
+            Ok(DataPaths {
+                data: data.join(PROFILES_DIR).join(&name),
+                config: config.join(PROFILES_DIR).join(&name),
+                profile: Some(name),
+            })
+        }
+        None => Ok(DataPaths {
+            data,
+            config,
+            profile: None,
+        }),
+    }
+}
+
+fn data_paths() -> &'static DataPaths {
+    DATA_PATHS.get().expect("data paths are resolved at startup")
+}
+
 fn iceland_dir() -> PathBuf {
-    let mut path = home_dir().expect("Could not find home directory");
-    path.push(format!(".{}", APP_NAME));
-    path
+    data_paths().data.clone()
+}
+
+fn config_dir() -> PathBuf {
+    data_paths().config.clone()
 }
 
 fn config_path() -> PathBuf {
-    iceland_dir().join(CONFIG_FILE)
+    config_dir().join(CONFIG_FILE)
 }
 
 fn current_area_file() -> PathBuf {
@@ -153,473 +1145,4195 @@ fn area_dir(area: &str) -> PathBuf {
     iceland_dir().join(area)
 }
 
-// ==============================================
-// CONFIG MANAGEMENT
-// ==============================================
+fn processes_file() -> PathBuf {
+    iceland_dir().join(PROCESSES_FILE)
+}
 
-fn load_config() -> Result<Config> {
-    let path = config_path();
-    if !path.exists() {
-        return Ok(Config::default());
-    }
-    let content = fs::read_to_string(path)?;
-    let config: Config = toml::from_str(&content)?;
-    Ok(config)
+fn lock_file() -> PathBuf {
+    iceland_dir().join(LOCK_FILE)
 }
 
-fn save_config(config: &Config) -> Result<()> {
-    let content = toml::to_string_pretty(config)?;
-    fs::write(config_path(), content)?;
-    Ok(())
+fn backup_dir() -> PathBuf {
+    iceland_dir().join(BACKUP_DIR)
 }
 
-// ==============================================
-// AREA HELPERS
-// ==============================================
+fn templates_dir() -> PathBuf {
+    iceland_dir().join(TEMPLATES_DIR)
+}
 
-fn area_exists(area: &str) -> bool {
-    area_dir(area).is_dir()
+fn trash_dir() -> PathBuf {
+    iceland_dir().join(TRASH_DIR)
 }
 
-fn read_current_area() -> Result<Option<String>> {
-    let path = current_area_file();
-    if !path.exists() {
-        return Ok(None);
-    }
-    let content = fs::read_to_string(path)?;
-    Ok(Some(content.trim().to_string()))
+fn index_file() -> PathBuf {
+    iceland_dir().join(INDEX_DIR).join(INDEX_FILE)
 }
 
-fn write_current_area(area: &str) -> Result<()> {
-    fs::write(current_area_file(), area)?;
-    Ok(())
+fn snapshots_dir() -> PathBuf {
+    iceland_dir().join(SNAPSHOTS_DIR)
 }
 
 // ==============================================
-// SESSION HANDLING
+// LOCKING & ATOMIC WRITES
 // ==============================================
 
-fn record_session(area: &str, start: DateTime<Local>, end: DateTime<Local>) -> Result<()> {
-    let file = sessions_file();
-    let mut wtr = csv::WriterBuilder::new()
-        .has_headers(!file.exists())
-        .from_path(&file)?;
-
-    let session = Session {
-        area: area.to_string(),
-        start,
-        end,
-    };
-    wtr.serialize(session)?;
-    wtr.flush()?;
-    Ok(())
+/// Advisory lock on the iceland directory, released when dropped.
+///
+/// Take it once at the top of a command, never in helpers: a second lock
+/// from the same process would block on the first.
+struct DataLock {
+    _file: File,
 }
 
-fn stop_current_session() -> Result<()> {
-    let start_file = session_start_file();
-    if !start_file.exists() {
-        return Err(anyhow!("No active session. Use `start` first."));
-    }
-
-    let start_str = fs::read_to_string(&start_file)?;
-    let start = DateTime::parse_from_rfc3339(&start_str)
-        .context("Invalid start time format")?
-        .with_timezone(&Local);
+fn lock_data_dir() -> Result<DataLock> {
+    fs::create_dir_all(iceland_dir())?;
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(lock_file())
+        .context("Could not open lock file")?;
 
-    let area = read_current_area()?.ok_or_else(|| anyhow!("No current area set"))?;
-    let end = Local::now();
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(fs::TryLockError::WouldBlock) => {
+            eprintln!("Waiting for another iceland process to finish...");
+            file.lock().context("Could not lock the data directory")?;
+        }
+        Err(fs::TryLockError::Error(e)) => {
+            return Err(e).context("Could not lock the data directory");
+        }
+    }
+    Ok(DataLock { _file: file })
+}
 
-    record_session(&area, start, end)?;
-    fs::remove_file(start_file)?;
-    println!("Stopped session for '{}' ({} seconds)", area, (end - start).num_seconds());
+/// Write a file via a temporary sibling and rename, so readers never see a partial write.
+fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("file");
+    let tmp = path.with_file_name(format!(".{}.{}.tmp", name, std::process::id()));
+    {
+        let mut file = File::create(&tmp)?;
+        file.write_all(contents.as_ref())?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp, path).with_context(|| format!("Could not write {}", path.display()))?;
     Ok(())
 }
 
 // ==============================================
-// INIT
+// CONFIG MANAGEMENT
 // ==============================================
 
-fn init_iceland() -> Result<()> {
-    let base = iceland_dir();
-    fs::create_dir_all(&base)?;
-
-    let config = Config::default();
-    save_config(&config)?;
-
-    for area in &config.areas {
-        create_area_structure(area)?;
-    }
-
-    // Set initial current area (first in list)
-    if let Some(first) = config.areas.first() {
-        write_current_area(first)?;
+fn load_config() -> Result<Config> {
+    let path = config_path();
+    if !path.exists() {
+        return Ok(Config::default());
     }
-
-    println!("✅ Iceland initialized in {}", base.display());
-    println!("Areas: {}", config.areas.join(", "));
-    Ok(())
+    let content = fs::read_to_string(path)?;
+    let config: Config = toml::from_str(&content)?;
+    Ok(config)
 }
 
-fn create_area_structure(area: &str) -> Result<()> {
-    let area_path = area_dir(area);
-    fs::create_dir_all(&area_path)?;
-
-    // Subdirectories common to all areas
-    fs::create_dir_all(area_path.join("notes"))?;
-    fs::create_dir_all(area_path.join("flashcards"))?;
-
-    // Area‑specific initialisation (can be extended)
-    match area {
-        "math" => {
-            fs::create_dir_all(area_path.join("browser_firefox"))?;
-            let mut links = File::create(area_path.join("links.txt"))?;
-            writeln!(links, "Math resources:")?;
-            writeln!(links, "https://www.khanacademy.org")?;
-        }
-        "learning" => {
-            fs::create_dir_all(area_path.join("browser_comet"))?;
-            let mut links = File::create(area_path.join("links.txt"))?;
-            writeln!(links, "Primuss: https://www3.primuss.de/")?;
-            writeln!(links, "Wikipedia: https://www.wikipedia.org")?;
-            writeln!(links, "ChatGPT: https://chat.openai.com")?;
-        }
-        "work" => {
-            fs::create_dir_all(area_path.join("projects"))?;
-            fs::create_dir_all(area_path.join("docs"))?;
-            fs::create_dir_all(area_path.join("browser_profile"))?;
-        }
-        "gaming" => {
-            fs::create_dir_all(area_path.join("games"))?;
-            fs::create_dir_all(area_path.join("clips"))?;
-            fs::create_dir_all(area_path.join("browser_profile"))?;
-        }
-        "traveling" => {
-            fs::create_dir_all(area_path.join("plans"))?;
-        }
-        "trading" => {
-            fs::create_dir_all(area_path.join("analysis"))?;
-        }
-        _ => {
-            // For custom areas, just create a basic structure
-            let mut links = File::create(area_path.join("links.txt"))?;
-            writeln!(links, "# Links for {}\n", area)?;
-        }
-    }
-
+fn save_config(config: &Config) -> Result<()> {
+    fs::create_dir_all(config_dir())?;
+    let content = toml::to_string_pretty(config)?;
+    write_atomic(&config_path(), content)?;
     Ok(())
 }
 
 // ==============================================
-// SWITCH
+// MIGRATIONS
 // ==============================================
 
-fn switch_area(new_area: &str) -> Result<()> {
-    if !area_exists(new_area) {
-        return Err(anyhow!("Area '{}' does not exist. Use `add-area` first.", new_area));
-    }
-
-    // Stop current session if any
-    let start_file = session_start_file();
-    if start_file.exists() {
-        if let Err(e) = stop_current_session() {
-            eprintln!("Warning: failed to stop previous session: {}", e);
-        }
-    }
-
-    // Start new session
-    fs::write(&start_file, Local::now().to_rfc3339())?;
-    write_current_area(new_area)?;
-
-    println!("🔄 Switched to area: {}", new_area);
-    println!("   Path: {}", area_dir(new_area).display());
-
-    // Show links if available
-    let links_path = area_dir(new_area).join("links.txt");
-    if links_path.exists() {
-        println!("\n📌 Useful links:");
-        let links = fs::read_to_string(links_path)?;
-        println!("{}", links);
-    }
-
-    // Launch browser if configured
-    let config = load_config()?;
-    let browser_cmd = config.browser_command.replace("{area}", new_area);
-    if !browser_cmd.is_empty() {
-        let mut parts = browser_cmd.split_whitespace();
-        if let Some(cmd) = parts.next() {
-            let args: Vec<_> = parts.collect();
-            if let Err(e) = Command::new(cmd).args(args).spawn() {
-                eprintln!("Warning: could not launch browser: {}", e);
-            } else {
-                println!("🌐 Launched browser for area '{}'", new_area);
-            }
-        }
-    }
-
-    Ok(())
+/// An upgrade step taking the data from `from` to `from + 1`. Steps must be
+/// idempotent, since a failure later in the chain re-runs them next time.
+struct Migration {
+    from: u32,
+    name: &'static str,
+    run: fn() -> Result<()>,
 }
 
-// ==============================================
-// STATUS
-// ==============================================
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 0,
+        name: "structured session files with ids",
+        run: migrate_v0_sessions,
+    },
+    Migration {
+        from: 1,
+        name: "per-area config tables",
+        run: migrate_v1_area_tables,
+    },
+    Migration {
+        from: 2,
+        name: "managed browser profiles",
+        run: migrate_v2_browser_profiles,
+    },
+    Migration {
+        from: 3,
+        name: "one Markdown file per note",
+        run: migrate_v3_markdown_notes,
+    },
+];
 
-fn show_status() -> Result<()> {
-    let current = read_current_area()?;
-    match current {
-        Some(area) => {
-            println!("📍 Current area: {}", area);
-            println!("   Path: {}", area_dir(&area).display());
+/// Read the schema version without deserializing `Config`, whose shape may have changed.
+fn read_schema_version() -> Result<u32> {
+    let content = fs::read_to_string(config_path())?;
+    let table: toml::Table = content.parse().context("config.toml is not valid TOML")?;
+    Ok(table
+        .get("schema_version")
+        .and_then(|v| v.as_integer())
+        .unwrap_or(0) as u32)
+}
 
-            if session_start_file().exists() {
-                println!("⏱️  Session timer is running.");
-            } else {
-                println!("⏸️  No active session.");
-            }
+/// Copy the top-level data files into `.backup/schema-v<version>-<timestamp>/`.
+fn backup_data_files(version: u32) -> Result<PathBuf> {
+    let dest = backup_dir().join(format!(
+        "schema-v{}-{}",
+        version,
+        Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    fs::create_dir_all(&dest)?;
+    let mut files = vec![config_path()];
+    for entry in fs::read_dir(iceland_dir())? {
+        let path = entry?.path();
+        if path.is_file() && path.file_name() != Some(LOCK_FILE.as_ref()) && !files.contains(&path) {
+            files.push(path);
         }
-        None => {
-            println!("❌ No current area set. Run `init` or `switch`.");
+    }
+    for path in files {
+        if let Some(name) = path.file_name() {
+            fs::copy(&path, dest.join(name))?;
         }
     }
-    Ok(())
+    Ok(dest)
 }
 
-// ==============================================
-// STATS
-// ==============================================
-
-fn show_stats() -> Result<()> {
-    let file = sessions_file();
-    if !file.exists() {
-        println!("No sessions recorded yet.");
+/// Bring an older ~/.iceland up to `SCHEMA_VERSION`. Runs before every command.
+fn run_migrations() -> Result<()> {
+    if !config_path().exists() || read_schema_version()? == SCHEMA_VERSION {
         return Ok(());
     }
 
-    let mut rdr = csv::Reader::from_path(file)?;
-    let mut totals: HashMap<String, i64> = HashMap::new();
-
-    for result in rdr.deserialize() {
-        let session: Session = result?;
-        let secs = (session.end - session.start).num_seconds();
-        *totals.entry(session.area).or_insert(0) += secs;
+    let _lock = lock_data_dir()?;
+    let version = read_schema_version()?;
+    if version > SCHEMA_VERSION {
+        return Err(anyhow!(
+            "{} was written by a newer iceland (schema {}, this build supports {}). Please upgrade.",
+            config_path().display(),
+            version,
+            SCHEMA_VERSION
+        ));
+    }
+    if version == SCHEMA_VERSION {
+        return Ok(());
     }
 
-    println!("\n+------------+------------------+");
-    println!("| Area       | Time (hh:mm)     |");
-    println!("+------------+------------------+");
-
-    for (area, secs) in totals {
-        let hours = secs / 3600;
-        let minutes = (secs % 3600) / 60;
-        println!("| {:<10} | {:>7}:{:02} h       |", area, hours, minutes);
+    let backup = backup_data_files(version)?;
+    println!(
+        "🔧 Upgrading {} from schema {} to {} (backup in {})",
+        iceland_dir().display(),
+        version,
+        SCHEMA_VERSION,
+        backup.display()
+    );
+    for migration in MIGRATIONS.iter().filter(|m| m.from >= version) {
+        (migration.run)().with_context(|| {
+            format!(
+                "Migration '{}' failed; your data is backed up in {}",
+                migration.name,
+                backup.display()
+            )
+        })?;
     }
 
-    println!("+------------+------------------+\n");
+    // Re-saving also writes any config fields added since
+    let mut config = load_config()?;
+    config.schema_version = SCHEMA_VERSION;
+    save_config(&config)?;
     Ok(())
 }
 
-// ==============================================
-// SESSION COMMANDS
-// ==============================================
-
-fn start_session() -> Result<()> {
-    let area = read_current_area()?.ok_or_else(|| anyhow!("No current area set"))?;
-    let start_file = session_start_file();
-    if start_file.exists() {
-        return Err(anyhow!("Session already started. Use `stop` first."));
+/// v0 -> v1: sessions.csv gains tag/note/planned/id columns and
+/// session_start becomes a TOML record instead of a bare timestamp.
+fn migrate_v0_sessions() -> Result<()> {
+    let content = repair_sessions_file()?;
+    if !content.is_empty() && content.lines().next() != Some(SESSIONS_HEADER) {
+        write_atomic(&sessions_file(), upgrade_sessions_csv(&content)?)?;
+    }
+    if let Some(running) = read_running_session()? {
+        write_running_session(&running)?;
     }
-    fs::write(&start_file, Local::now().to_rfc3339())?;
-    println!("▶️  Timer started for area: {}", area);
     Ok(())
 }
 
-fn stop_session() -> Result<()> {
-    stop_current_session()
+/// v1 -> v2: the flat `areas` list becomes `[areas.<name>]` tables.
+/// `Config` accepts both shapes, so loading and saving is enough.
+fn migrate_v1_area_tables() -> Result<()> {
+    save_config(&load_config()?)
 }
 
-// ==============================================
-// DESTROY
-// ==============================================
-
-fn destroy_in_area(area: &str, target: DestroyTarget) -> Result<()> {
-    let area_path = area_dir(area);
-    if !area_path.exists() {
-        return Err(anyhow!("Area '{}' does not exist.", area));
+/// v2 -> v3: the old default `firefox -P {area}` (Firefox's global profile
+/// registry) gives way to profiles inside each area. Custom commands are kept.
+fn migrate_v2_browser_profiles() -> Result<()> {
+    let mut config = load_config()?;
+    if config.browser_command == LEGACY_BROWSER_COMMAND {
+        config.browser_command.clear();
     }
+    save_config(&config)
+}
 
-    match target {
-        DestroyTarget::Browser => {
-            let browser_dirs = ["browser_firefox", "browser_comet", "browser_profile"];
-            for name in &browser_dirs {
-                let dir = area_path.join(name);
-                if dir.exists() {
-                    fs::remove_dir_all(&dir)?;
-                    fs::create_dir_all(&dir)?;
-                    println!("♻️  Reset browser directory: {}", name);
-                }
-            }
+/// v3 -> v4: each line of `notes/my_notes.txt` becomes its own note, dated
+/// with the file's modification time. The old file stays as `.my_notes.txt.migrated`.
+fn migrate_v3_markdown_notes() -> Result<()> {
+    for area in load_config()?.areas.keys() {
+        let notes_dir = area_dir(area).join("notes");
+        let legacy = notes_dir.join(LEGACY_NOTES_FILE);
+        if !legacy.exists() {
+            continue;
         }
-        DestroyTarget::Notes => {
-            let notes = area_path.join("notes");
-            if notes.exists() {
-                fs::remove_dir_all(&notes)?;
-                fs::create_dir_all(&notes)?;
-                println!("♻️  Reset notes for {}", area);
-            } else {
-                println!("No notes folder found for {}", area);
-            }
+        let modified: DateTime<Local> = fs::metadata(&legacy)?.modified()?.into();
+        let content = fs::read_to_string(&legacy)?;
+        let lines = content.lines().filter(|line| !line.trim().is_empty());
+        for (i, line) in lines.enumerate() {
+            let id = format!("{}-{}", modified.format("%Y%m%d-%H%M%S"), i + 1);
+            write_note(&Note {
+                path: notes_dir.join(format!("{}.md", id)),
+                id,
+                title: None,
+                area: area.clone(),
+                tags: Vec::new(),
+                created: modified,
+                updated: modified,
+                body: line.to_string(),
+            })?;
         }
+        fs::rename(&legacy, notes_dir.join(format!(".{}.migrated", LEGACY_NOTES_FILE)))?;
     }
     Ok(())
 }
 
 // ==============================================
-// NOTES
+// AREA HELPERS
 // ==============================================
 
-fn add_note(area: &str, text: &str) -> Result<()> {
-    let notes_dir = area_dir(area).join("notes");
-    if !notes_dir.exists() {
-        return Err(anyhow!("Area '{}' does not exist or has no notes folder.", area));
+/// Area name with its configured icon and color, for display.
+fn styled_area_name(name: &str, area: &AreaConfig) -> String {
+    let label = match &area.icon {
+        Some(icon) => format!("{} {}", icon, name),
+        None => name.to_string(),
+    };
+    match &area.color {
+        Some(color) => Style::from_dotted_str(color).apply_to(label).to_string(),
+        None => label,
     }
+}
 
-    let notes_file = notes_dir.join("my_notes.txt");
-    let mut file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&notes_file)?;
+fn area_exists(area: &str) -> bool {
+    area_dir(area).is_dir()
+}
+
+fn read_current_area() -> Result<Option<String>> {
+    let path = current_area_file();
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path)?;
+    Ok(Some(content.trim().to_string()))
+}
 
-    writeln!(file, "{}", text)?;
-    println!("📝 Note added to {}", notes_file.display());
+fn write_current_area(area: &str) -> Result<()> {
+    write_atomic(&current_area_file(), area)?;
     Ok(())
 }
 
 // ==============================================
-// FLASHCARDS
+// SESSION HANDLING
 // ==============================================
 
-fn tui_flashcards(area: &str) -> Result<()> {
-    let flashcards_dir = area_dir(area).join("flashcards");
-    if !flashcards_dir.exists() {
-        return Err(anyhow!("No flashcards found for area '{}'.", area));
-    }
+/// Drop a truncated last row (e.g. from a crash mid-write) from the sessions CSV.
+///
+/// Works on CSV records rather than lines, so quoted notes spanning several
+/// lines survive. Returns the repaired content, or `None` if nothing needed fixing.
+fn repair_sessions_csv(content: &str) -> Option<String> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let headers = rdr.headers().ok()?.clone();
 
-    let mut decks = vec![];
-    for entry in fs::read_dir(&flashcards_dir)? {
-        let entry = entry?;
-        let path = entry.path();
-        if path.is_file() {
-            if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                decks.push(name.to_string());
-            }
+    // Byte offset where the last record starts, and the record itself
+    let mut last = None;
+    let mut record = csv::StringRecord::new();
+    loop {
+        let start = rdr.position().byte() as usize;
+        match rdr.read_record(&mut record) {
+            Ok(true) => last = Some((start, record.clone())),
+            Ok(false) => break,
+            Err(_) => return None,
         }
     }
+    let (start, record) = last?;
 
-    if decks.is_empty() {
-        println!("No flashcard decks available.");
-        return Ok(());
+    // A cut-off quoted field leaves an odd number of quotes; the reader would
+    // otherwise accept everything up to the end of the file as its value
+    let quotes_balanced = content[start..].matches('"').count().is_multiple_of(2);
+    if quotes_balanced && content.ends_with('\n') {
+        // A complete row; if it doesn't parse that's for `doctor`, not a crash
+        return None;
     }
-
-    let selection = Select::with_theme(&ColorfulTheme::default())
-        .with_prompt("Select a deck")
-        .items(&decks)
-        .default(0)
-        .interact()?;
-
-    let deck_path = flashcards_dir.join(&decks[selection]);
-    let file = File::open(deck_path)?;
-    let reader = BufReader::new(file);
-
-    let mut cards = vec![];
-    for (i, line) in reader.lines().enumerate() {
-        let line = line?;
-        if let Some((front, back)) = line.split_once('|') {
-            cards.push((front.to_string(), back.to_string()));
-        } else {
-            eprintln!("Warning: line {} skipped (no '|' separator)", i + 1);
-        }
+    if quotes_balanced && record.deserialize::<Session>(Some(&headers)).is_ok() {
+        // Row is intact, only the newline is missing
+        return Some(format!("{}\n", content));
     }
+    Some(content[..start].to_string())
+}
 
-    if cards.is_empty() {
-        println!("Deck is empty or malformed.");
-        return Ok(());
+/// Check sessions.csv for a truncated last row and rewrite it if needed.
+/// Caller must hold the data lock.
+fn repair_sessions_file() -> Result<String> {
+    let file = sessions_file();
+    if !file.exists() {
+        return Ok(String::new());
     }
-
-    println!("\n--- Starting flashcards ---");
-    for (i, (front, back)) in cards.iter().enumerate() {
-        println!("\nCard {} of {}", i + 1, cards.len());
-        println!("Front: {}", front);
-        println!("Press Enter to reveal back...");
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-        println!("Back: {}", back);
-        if i < cards.len() - 1 {
-            println!("Press Enter for next card...");
-            io::stdin().read_line(&mut input)?;
+    let content = fs::read_to_string(&file)?;
+    match repair_sessions_csv(&content) {
+        Some(repaired) => {
+            eprintln!("Warning: repaired truncated last row in {}", file.display());
+            write_atomic(&file, &repaired)?;
+            Ok(repaired)
         }
+        None => Ok(content),
     }
-    println!("--- Finished deck ---");
-    Ok(())
 }
 
-// ==============================================
-// AREA MANAGEMENT
-// ==============================================
+/// Give sessions from older files (without an id column) ids following the highest one.
+/// Assignment only depends on file order, so it is the same on every read.
+fn assign_missing_ids(sessions: &mut [Session]) {
+    let first = next_session_id(sessions);
+    for (id, session) in (first..).zip(sessions.iter_mut().filter(|s| s.id == 0)) {
+        session.id = id;
+    }
+}
 
-fn list_areas() -> Result<()> {
-    let config = load_config()?;
-    let current = read_current_area()?.unwrap_or_default();
+fn next_session_id(sessions: &[Session]) -> u64 {
+    sessions.iter().map(|s| s.id).max().unwrap_or(0) + 1
+}
 
-    println!("Available areas:");
-    for area in &config.areas {
-        let marker = if *area == current { "▶" } else { " " };
-        println!("  {} {}", marker, area);
+/// Parse every row of sessions.csv, failing on the first row that cannot be read.
+fn parse_sessions_strict(content: &str) -> Result<Vec<Session>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let mut sessions = Vec::new();
+    for (i, result) in rdr.deserialize::<Session>().enumerate() {
+        let session: Session =
+            result.with_context(|| format!("Could not parse sessions.csv row {}", i + 2))?;
+        sessions.push(session);
     }
-    Ok(())
+    assign_missing_ids(&mut sessions);
+    Ok(sessions)
 }
 
-fn add_area(name: &str) -> Result<()> {
-    let mut config = load_config()?;
-    if config.areas.iter().any(|a| a == name) {
-        return Err(anyhow!("Area '{}' already exists.", name));
+fn serialize_sessions(sessions: &[Session]) -> Result<Vec<u8>> {
+    let mut wtr = csv::Writer::from_writer(vec![]);
+    for session in sessions {
+        wtr.serialize(session)?;
     }
-
-    create_area_structure(name)?;
-    config.areas.push(name.to_string());
-    save_config(&config)?;
-    println!("✅ Area '{}' created.", name);
-    Ok(())
+    wtr.into_inner().map_err(|e| anyhow!("{}", e))
 }
 
-fn remove_area(name: &str) -> Result<()> {
-    let mut config = load_config()?;
-    if !config.areas.iter().any(|a| a == name) {
-        return Err(anyhow!("Area '{}' not found.", name));
+/// Rewrite sessions.csv content written with an older column layout.
+/// Rows that don't parse are kept verbatim at the end for `doctor` to deal with.
+fn upgrade_sessions_csv(content: &str) -> Result<String> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let headers = rdr.headers()?.clone();
+    let mut sessions = Vec::new();
+    let mut broken = Vec::new();
+    for result in rdr.records() {
+        let record = result?;
+        match record.deserialize::<Session>(Some(&headers)) {
+            Ok(session) => sessions.push(session),
+            Err(_) => broken.push(record),
+        }
     }
+    assign_missing_ids(&mut sessions);
 
-    // Confirm deletion
-    println!("WARNING: This will delete all data for area '{}' (notes, flashcards, etc.).", name);
-    print!("Type 'yes' to confirm: ");
-    io::stdout().flush()?;
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
-    if input.trim() != "yes" {
-        println!("Aborted.");
-        return Ok(());
+    let mut upgraded = serialize_sessions(&sessions)?;
+    if upgraded.is_empty() {
+        upgraded = format!("{}\n", SESSIONS_HEADER).into_bytes();
     }
-
-    // Remove directory
-    let area_path = area_dir(name);
-    if area_path.exists() {
-        fs::remove_dir_all(&area_path)?;
+    let mut wtr = csv::WriterBuilder::new().flexible(true).from_writer(upgraded);
+    for record in &broken {
+        wtr.write_record(record)?;
     }
+    Ok(String::from_utf8(wtr.into_inner().map_err(|e| anyhow!("{}", e))?)?)
+}
 
-    config.areas.retain(|a| a != name);
-    save_config(&config)?;
+/// Load every session for rewriting. Caller must hold the data lock.
+fn load_sessions_for_edit() -> Result<Vec<Session>> {
+    let content = repair_sessions_file()?;
+    parse_sessions_strict(&content)
+        .context("Fix or remove the broken row in sessions.csv before editing sessions")
+}
 
-    // If current area was removed, switch to first available or clear
-    if let Some(current) = read_current_area()? {
-        if current == name {
-            if let Some(first) = config.areas.first() {
+/// Replace sessions.csv with the given sessions. Caller must hold the data lock.
+fn save_sessions(sessions: &[Session]) -> Result<()> {
+    write_atomic(&sessions_file(), serialize_sessions(sessions)?)
+}
+
+/// Append a session to sessions.csv, assigning its id. Caller must hold the data lock.
+fn record_session(session: &Session) -> Result<()> {
+    let mut existing = repair_sessions_file()?;
+    if !existing.is_empty() && existing.lines().next() != Some(SESSIONS_HEADER) {
+        existing = upgrade_sessions_csv(&existing)?;
+    }
+    let id = next_session_id(&parse_sessions(&existing)?);
+
+    let mut wtr = csv::WriterBuilder::new()
+        .has_headers(existing.is_empty())
+        .from_writer(existing.into_bytes());
+    wtr.serialize(Session { id, ..session.clone() })?;
+    let data = wtr.into_inner().map_err(|e| anyhow!("{}", e))?;
+    write_atomic(&sessions_file(), data)?;
+    Ok(())
+}
+
+/// Read all recorded sessions, skipping rows that cannot be parsed.
+fn load_sessions() -> Result<Vec<Session>> {
+    let file = sessions_file();
+    if !file.exists() {
+        return Ok(Vec::new());
+    }
+    parse_sessions(&fs::read_to_string(file)?)
+}
+
+fn parse_sessions(content: &str) -> Result<Vec<Session>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let mut sessions = Vec::new();
+
+    // Manually read each record instead of deserializing
+    for result in rdr.records() {
+        let record = result?;
+        if record.len() >= 3 {
+            let area = record[0].to_string();
+            let start_str = record[1].to_string();
+            let end_str = record[2].to_string();
+
+            // Parse the datetime strings
+            if let (Ok(start), Ok(end)) = (
+                DateTime::parse_from_rfc3339(&start_str),
+                DateTime::parse_from_rfc3339(&end_str)
+            ) {
+                let optional = |i: usize| record.get(i).filter(|f| !f.is_empty()).map(String::from);
+                sessions.push(Session {
+                    area,
+                    start: start.with_timezone(&Local),
+                    end: end.with_timezone(&Local),
+                    tag: optional(3),
+                    note: optional(4),
+                    planned: optional(5).and_then(|p| p.parse().ok()),
+                    id: optional(6).and_then(|id| id.parse().ok()).unwrap_or(0),
+                });
+            }
+        }
+    }
+    assign_missing_ids(&mut sessions);
+    Ok(sessions)
+}
+
+fn read_running_session() -> Result<Option<RunningSession>> {
+    let path = session_start_file();
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path)?;
+    if let Ok(running) = toml::from_str(&content) {
+        return Ok(Some(running));
+    }
+
+    // Older versions stored a bare RFC3339 timestamp for the current area
+    let start = DateTime::parse_from_rfc3339(content.trim())
+        .context("Invalid session_start format")?
+        .with_timezone(&Local);
+    let area = read_current_area()?.ok_or_else(|| anyhow!("No current area set"))?;
+    Ok(Some(RunningSession {
+        start,
+        ..RunningSession::new(&area)
+    }))
+}
+
+fn write_running_session(running: &RunningSession) -> Result<()> {
+    write_atomic(&session_start_file(), toml::to_string(running)?)
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{}:{:02}", duration.num_hours(), duration.num_minutes() % 60)
+}
+
+/// Parse durations like `25m`, `1h30m`, `90s`, `30d` or a bare number of minutes.
+fn parse_duration(input: &str) -> Result<Duration> {
+    let input = input.trim();
+    if let Ok(minutes) = input.parse::<i64>() {
+        if minutes <= 0 {
+            return Err(anyhow!("Invalid duration '{}' (must be positive)", input));
+        }
+        return Ok(Duration::minutes(minutes));
+    }
+
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    for c in input.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let value: i64 = number
+            .parse()
+            .map_err(|_| anyhow!("Invalid duration '{}'", input))?;
+        total += match c {
+            'd' => Duration::days(value),
+            'h' => Duration::hours(value),
+            'm' => Duration::minutes(value),
+            's' => Duration::seconds(value),
+            _ => return Err(anyhow!("Invalid duration '{}' (use e.g. 25m, 1h30m)", input)),
+        };
+        number.clear();
+    }
+    if !number.is_empty() || total <= Duration::zero() {
+        return Err(anyhow!("Invalid duration '{}' (use e.g. 25m, 1h30m)", input));
+    }
+    Ok(total)
+}
+
+/// Record a timeboxed session whose deadline has passed, ending it at the deadline.
+/// Returns the closed session so the caller can run its hooks once unlocked.
+/// Caller must hold the data lock.
+fn close_expired_session_locked() -> Result<Option<Session>> {
+    let Some(running) = read_running_session()? else {
+        return Ok(None);
+    };
+    let Some(deadline) = running.deadline().filter(|d| *d <= Local::now()) else {
+        return Ok(None);
+    };
+
+    let session = running.finish(deadline);
+    record_session(&session)?;
+    fs::remove_file(session_start_file())?;
+    println!(
+        "⏰ Timeboxed session for '{}' ended at {}",
+        session.area,
+        deadline.format("%Y-%m-%d %H:%M")
+    );
+    Ok(Some(session))
+}
+
+/// Run on every invocation so timeboxed sessions close even if nobody was watching.
+fn close_expired_session() -> Result<()> {
+    let expired = read_running_session()?
+        .and_then(|r| r.deadline())
+        .is_some_and(|d| d <= Local::now());
+    if expired {
+        let closed = {
+            let _lock = lock_data_dir()?;
+            close_expired_session_locked()?
+        };
+        if let Some(session) = closed {
+            run_session_stop_hooks(&session)?;
+        }
+    }
+    Ok(())
+}
+
+/// Parse a day: `today`, `yesterday` or `YYYY-MM-DD`.
+fn parse_date(input: &str) -> Result<NaiveDate> {
+    let today = Local::now().date_naive();
+    match input.trim() {
+        "today" => Ok(today),
+        "yesterday" => Ok(today - Duration::days(1)),
+        other => NaiveDate::parse_from_str(other, "%Y-%m-%d")
+            .map_err(|_| anyhow!("Invalid date '{}' (use today, yesterday or YYYY-MM-DD)", other)),
+    }
+}
+
+/// Parse a point in time: `now`, `-2h` (relative to now), `HH:MM` (its most
+/// recent occurrence), `<date> HH:MM` (see `parse_date`) or RFC3339.
+fn parse_time(input: &str) -> Result<DateTime<Local>> {
+    parse_time_on(input, None)
+}
+
+/// Like `parse_time`, but a bare `HH:MM` refers to `day` when given.
+fn parse_time_on(input: &str, day: Option<NaiveDate>) -> Result<DateTime<Local>> {
+    let input = input.trim();
+    if input == "now" {
+        return Ok(Local::now());
+    }
+    if let Some(ago) = input.strip_prefix('-') {
+        return Ok(Local::now() - parse_duration(ago)?);
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(input) {
+        return Ok(time.with_timezone(&Local));
+    }
+
+    let (date_part, time_part) = match input.rsplit_once(' ') {
+        Some((date, time)) => (Some(date), time),
+        None => (None, input),
+    };
+    let time = NaiveTime::parse_from_str(time_part, "%H:%M").map_err(|_| {
+        anyhow!("Invalid time '{}' (use HH:MM, yesterday HH:MM, YYYY-MM-DD HH:MM or -2h)", input)
+    })?;
+    let date = match date_part {
+        Some(date) => Some(parse_date(date)?),
+        None => day,
+    };
+
+    let naive = match date {
+        Some(date) => date.and_time(time),
+        None => {
+            let now = Local::now();
+            let today = now.date_naive().and_time(time);
+            if today > now.naive_local() {
+                today - Duration::days(1)
+            } else {
+                today
+            }
+        }
+    };
+
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .ok_or_else(|| anyhow!("Time '{}' does not exist in the local timezone", input))
+}
+
+/// Decide where a running session ends when no explicit end was given.
+///
+/// Sessions longer than `max_session` were most likely forgotten, so the
+/// user is offered to truncate them. Returns `None` to discard the session.
+fn resolve_session_end(running: &RunningSession) -> Result<Option<DateTime<Local>>> {
+    let now = Local::now();
+    let max = load_config()?.max_session_length();
+    let elapsed = now - running.start;
+    if elapsed <= max {
+        return Ok(Some(now));
+    }
+
+    eprintln!(
+        "⚠️  Session in '{}' has been running for {} h (limit {} h).",
+        running.area,
+        format_duration(elapsed),
+        format_duration(max)
+    );
+    if !io::stdin().is_terminal() {
+        eprintln!("   Not a terminal, recording it as-is. Use `stop --at <time>` to set the real end.");
+        return Ok(Some(now));
+    }
+
+    let truncated = running.start + max;
+    let options = [
+        format!("Truncate to {} h (ends {})", format_duration(max), truncated.format("%Y-%m-%d %H:%M")),
+        format!("Keep the full {} h", format_duration(elapsed)),
+        "Enter the actual end time".to_string(),
+        "Discard the session".to_string(),
+    ];
+    let choice = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Forgotten timer?")
+        .items(&options)
+        .default(0)
+        .interact()?;
+
+    match choice {
+        0 => Ok(Some(truncated)),
+        1 => Ok(Some(now)),
+        2 => loop {
+            // Ask again on a typo instead of giving up on the session
+            let input: String = Input::with_theme(&ColorfulTheme::default())
+                .with_prompt("End time (HH:MM or YYYY-MM-DD HH:MM)")
+                .interact_text()?;
+            match parse_time(&input) {
+                Ok(end) if end <= running.start => eprintln!(
+                    "End time must be after the session start ({}).",
+                    running.start.format("%Y-%m-%d %H:%M")
+                ),
+                Ok(end) if end > now => eprintln!("End time must not be in the future."),
+                Ok(end) => break Ok(Some(end)),
+                Err(e) => eprintln!("{}", e),
+            }
+        },
+        _ => Ok(None),
+    }
+}
+
+/// Record the running session that began at `started`, or discard it (`None`).
+/// Decide `end` (e.g. with `resolve_session_end`) before locking; caller must
+/// hold the data lock and run `run_session_stop_hooks` after releasing it.
+fn stop_current_session(started: DateTime<Local>, end: Option<DateTime<Local>>) -> Result<Option<Session>> {
+    let running = read_running_session()?
+        .ok_or_else(|| anyhow!("No active session. Use `start` first."))?;
+    if running.start != started {
+        return Err(anyhow!("The running session changed in the meantime. Try again."));
+    }
+
+    let Some(end) = end else {
+        fs::remove_file(session_start_file())?;
+        println!("🗑️  Discarded session for '{}'", running.area);
+        return Ok(None);
+    };
+    if end <= running.start {
+        return Err(anyhow!(
+            "End time {} is before the session start ({})",
+            end.format("%Y-%m-%d %H:%M"),
+            running.start.format("%Y-%m-%d %H:%M")
+        ));
+    }
+    if end > Local::now() {
+        return Err(anyhow!("End time {} is in the future", end.format("%Y-%m-%d %H:%M")));
+    }
+
+    let session = running.finish(end);
+    record_session(&session)?;
+    fs::remove_file(session_start_file())?;
+    let seconds = (session.end - session.start).num_seconds();
+    println!("Stopped session for '{}' ({} seconds)", session.area, seconds);
+    Ok(Some(session))
+}
+
+// ==============================================
+// INIT
+// ==============================================
+
+fn init_iceland() -> Result<()> {
+    let base = iceland_dir();
+    let _lock = lock_data_dir()?;
+
+    let mut config = Config::default();
+
+    for (area, area_config) in config.areas.iter_mut() {
+        let template = load_template(&default_template_for(area))?;
+        create_area_structure(area, &template)?;
+        *area_config = template.area;
+    }
+
+    save_config(&config)?;
+
+    // Set initial current area (first in list)
+    if let Some(first) = config.areas.keys().next() {
+        write_current_area(first)?;
+    }
+
+    println!("✅ Iceland initialized in {}", base.display());
+    if config_dir() != base {
+        println!("Config: {}", config_path().display());
+    }
+    let names: Vec<_> = config.areas.keys().map(String::as_str).collect();
+    println!("Areas: {}", names.join(", "));
+    Ok(())
+}
+
+/// Join a path from a template onto the area dir, refusing anything that could
+/// land outside it (absolute paths, `..`).
+fn template_dest(area_path: &Path, relative: &str) -> Result<PathBuf> {
+    let path = Path::new(relative);
+    if relative.is_empty() || path.components().any(|c| !matches!(c, Component::Normal(_))) {
+        return Err(anyhow!("Refusing unsafe path '{}' in the template.", relative));
+    }
+    Ok(area_path.join(path))
+}
+
+fn create_area_structure(area: &str, template: &Template) -> Result<()> {
+    let area_path = area_dir(area);
+    // Check every path up front so a bad template leaves no half-made area behind
+    let directories = template.directories.iter().chain(&template.area.directories);
+    for path in directories.chain(template.files.keys()) {
+        template_dest(&area_path, path)?;
+    }
+    for deck in template.flashcards.keys() {
+        template_dest(&area_path.join("flashcards"), deck)?;
+    }
+    fs::create_dir_all(&area_path)?;
+
+    // Subdirectories common to all areas
+    fs::create_dir_all(area_path.join("notes"))?;
+    fs::create_dir_all(area_path.join("flashcards"))?;
+
+    if let Some(source) = &template.source_dir {
+        copy_dir_recursive(source, &area_path, &[TEMPLATE_MANIFEST])?;
+    }
+
+    let directories = template.directories.iter().chain(&template.area.directories);
+    for dir in directories {
+        fs::create_dir_all(template_dest(&area_path, dir)?)?;
+    }
+
+    if !template.links.is_empty() {
+        let mut links = File::create(area_path.join("links.txt"))?;
+        for line in &template.links {
+            writeln!(links, "{}", line.replace("{area}", area))?;
+        }
+    }
+
+    for (path, content) in &template.files {
+        let dest = template_dest(&area_path, path)?;
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(dest, content.replace("{area}", area))?;
+    }
+
+    for (deck, cards) in &template.flashcards {
+        let mut file = File::create(template_dest(&area_path.join("flashcards"), deck)?)?;
+        for card in cards {
+            writeln!(file, "{}", card)?;
+        }
+    }
+
+    Ok(())
+}
+
+// ==============================================
+// SWITCH
+// ==============================================
+
+fn switch_area(new_area: &str, launch: bool) -> Result<()> {
+    if !area_exists(new_area) {
+        return Err(anyhow!("Area '{}' does not exist. Use `add-area` first.", new_area));
+    }
+    if load_config()?.area(new_area).archived {
+        return Err(anyhow!("Area '{}' is archived. Use `unarchive-area` first.", new_area));
+    }
+
+    let previous_area = read_current_area()?;
+    // Asked before locking, so an unanswered prompt doesn't block other commands
+    let running = read_running_session()?;
+    let end = match &running {
+        Some(running) => resolve_session_end(running)?,
+        None => None,
+    };
+
+    // Runs before anything changes (and before locking, so hooks may call
+    // iceland themselves); an aborting on_leave cancels the switch
+    if let Some(previous) = previous_area.as_deref().filter(|p| *p != new_area) {
+        let mut env = vec![("ICELAND_NEXT_AREA", new_area.to_string())];
+        if let Some(running) = running.as_ref().filter(|r| r.area == previous) {
+            env.push(("ICELAND_SESSION_SECONDS", (Local::now() - running.start).num_seconds().to_string()));
+        }
+        run_hooks(HookEvent::Leave, previous, &env)?;
+    }
+
+    let lock = lock_data_dir()?;
+    // Stop current session if any; never replace a session that could not be recorded
+    let stopped = match &running {
+        Some(running) => stop_current_session(running.start, end)
+            .context("Could not stop the running session, switch cancelled")?,
+        None if session_start_file().exists() => {
+            return Err(anyhow!("A session was started in the meantime, switch cancelled"));
+        }
+        None => None,
+    };
+
+    // Start new session
+    write_running_session(&RunningSession::new(new_area))?;
+    write_current_area(new_area)?;
+
+    let config = load_config()?;
+    if let Some(previous) = previous_area.as_deref().filter(|p| *p != new_area) {
+        if let Err(e) = close_area_apps(&config, previous) {
+            eprintln!("Warning: could not close apps from '{}': {}", previous, e);
+        }
+    }
+
+    let area_config = config.area(new_area);
+    println!("🔄 Switched to area: {}", styled_area_name(new_area, &area_config));
+    if let Some(description) = &area_config.description {
+        println!("   {}", description);
+    }
+    println!("   Path: {}", area_dir(new_area).display());
+
+    // Show links if available
+    let links_path = area_dir(new_area).join("links.txt");
+    if !area_config.links.is_empty() || links_path.exists() {
+        println!("\n📌 Useful links:");
+        for link in &area_config.links {
+            println!("{}", link);
+        }
+        if links_path.exists() {
+            let links = fs::read_to_string(links_path)?;
+            println!("{}", links);
+        }
+    }
+
+    // Foreground launch actions can run for a long time; don't block other commands
+    drop(lock);
+    // The switch already happened, so even an aborting hook only warns here
+    if let Some(session) = stopped {
+        if let Err(e) = run_session_stop_hooks(&session) {
+            eprintln!("Warning: {}", e);
+        }
+    }
+    let prev_env: Vec<_> = previous_area
+        .iter()
+        .map(|prev| ("ICELAND_PREV_AREA", prev.clone()))
+        .collect();
+    for event in [HookEvent::Enter, HookEvent::SessionStart] {
+        if let Err(e) = run_hooks(event, new_area, &prev_env) {
+            eprintln!("Warning: {}", e);
+        }
+    }
+    if launch {
+        let mut launched = Vec::new();
+        for action in launch_actions(&config, new_area) {
+            let label = action.name.as_deref().unwrap_or(&action.command);
+            match run_launch_action(&action, new_area) {
+                Ok(process) => {
+                    println!("🚀 Launched {}", label);
+                    launched.extend(process);
+                }
+                Err(e) => eprintln!("Warning: could not launch {}: {}", label, e),
+            }
+        }
+
+        if !launched.is_empty() {
+            let _lock = lock_data_dir()?;
+            let mut state = load_launched_processes()?;
+            state.processes.extend(launched);
+            save_launched_processes(&state)?;
+        }
+    }
+
+    Ok(())
+}
+
+// ==============================================
+// LAUNCHERS
+// ==============================================
+
+/// Fill in `{area}`, `{area_dir}`, `{notes_dir}` and `{date}`.
+fn expand_placeholders(text: &str, area: &str) -> String {
+    let area_path = area_dir(area);
+    text.replace("{area_dir}", &area_path.to_string_lossy())
+        .replace("{notes_dir}", &area_path.join("notes").to_string_lossy())
+        .replace("{date}", &Local::now().format("%Y-%m-%d").to_string())
+        .replace("{area}", area)
+}
+
+/// The command that opens the area's browser, most specific setting first:
+/// the area's `browser_command`, its `browser`, the global `browser_command`,
+/// then the global `browser`. Managed profiles are created on the way.
+fn browser_command_for(config: &Config, area: &str) -> Option<String> {
+    let area_config = config.area(area);
+    if let Some(command) = area_config.browser_command {
+        return Some(command);
+    }
+    let browser = match area_config.browser {
+        Some(browser) => browser,
+        None if !config.browser_command.trim().is_empty() => return Some(config.browser_command.clone()),
+        None => config.browser,
+    };
+    if browser != Browser::Disabled {
+        if let Err(e) = ensure_browser_profile(browser, area) {
+            eprintln!("Warning: could not set up the {:?} profile: {}", browser, e);
+        }
+    }
+    browser.command()
+}
+
+/// The browser followed by the area's launch list.
+fn launch_actions(config: &Config, area: &str) -> Vec<LaunchAction> {
+    let mut actions = Vec::new();
+    if let Some(browser) = browser_command_for(config, area).filter(|c| !c.trim().is_empty()) {
+        actions.push(LaunchAction {
+            name: Some("browser".to_string()),
+            command: browser,
+            ..LaunchAction::default()
+        });
+    }
+    actions.extend(config.area(area).launch);
+    actions
+}
+
+// ==============================================
+// BROWSER PROFILES
+// ==============================================
+
+impl Browser {
+    /// Browsers with a profile directory, in the order `destroy` reports them.
+    const MANAGED: &'static [Browser] = &[
+        Browser::Firefox,
+        Browser::Chromium,
+        Browser::Chrome,
+        Browser::Brave,
+        Browser::Comet,
+    ];
+
+    fn program(self) -> &'static str {
+        match self {
+            Browser::Firefox => "firefox",
+            Browser::Chromium => "chromium",
+            Browser::Chrome => "google-chrome",
+            Browser::Brave => "brave-browser",
+            Browser::Comet => "comet",
+            Browser::Disabled => "",
+        }
+    }
+
+    fn profile_dir(self) -> &'static str {
+        match self {
+            Browser::Firefox => "browser_firefox",
+            Browser::Chromium => "browser_chromium",
+            Browser::Chrome => "browser_chrome",
+            Browser::Brave => "browser_brave",
+            Browser::Comet => "browser_comet",
+            Browser::Disabled => "",
+        }
+    }
+
+    /// Launch command pointing the browser at the area's own profile.
+    fn command(self) -> Option<String> {
+        match self {
+            Browser::Disabled => None,
+            Browser::Firefox => Some(format!(
+                "firefox --no-remote --profile '{{area_dir}}/{}'",
+                self.profile_dir()
+            )),
+            _ => Some(format!(
+                "{} '--user-data-dir={{area_dir}}/{}'",
+                self.program(),
+                self.profile_dir()
+            )),
+        }
+    }
+}
+
+/// Create the area's profile for `browser` and seed it with `user.js` (Firefox)
+/// or `Default/Preferences` (Chromium family). Profiles with content are left alone.
+fn ensure_browser_profile(browser: Browser, area: &str) -> Result<()> {
+    let dir = area_dir(area).join(browser.profile_dir());
+    if dir.is_dir() && fs::read_dir(&dir)?.next().is_some() {
+        return Ok(());
+    }
+    fs::create_dir_all(&dir)?;
+
+    let (seed_file, builtin, dest) = match browser {
+        Browser::Firefox => (FIREFOX_SEED_FILE, FIREFOX_USER_JS, dir.join("user.js")),
+        _ => (CHROMIUM_SEED_FILE, CHROMIUM_PREFERENCES, dir.join("Default").join("Preferences")),
+    };
+    let seed = fs::read_to_string(templates_dir().join(seed_file)).unwrap_or_else(|_| builtin.to_string());
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(dest, seed.replace("{area}", area))?;
+    if browser != Browser::Firefox {
+        // Skips the welcome and default-browser prompts
+        fs::write(dir.join("First Run"), "")?;
+    }
+    Ok(())
+}
+
+/// Run one launch action. Returns the process to remember if it was detached.
+fn run_launch_action(action: &LaunchAction, area: &str) -> Result<Option<LaunchedProcess>> {
+    // Split before expanding so paths with spaces stay one argument
+    let words = shell_words::split(&action.command)
+        .with_context(|| format!("Invalid launch command '{}'", action.command))?;
+    let mut words = words.iter().map(|word| expand_placeholders(word, area));
+    let program = words.next().ok_or_else(|| anyhow!("empty command"))?;
+
+    let mut command = Command::new(&program);
+    command.args(words);
+    if let Some(cwd) = &action.cwd {
+        command.current_dir(expand_placeholders(cwd, area));
+    }
+
+    if action.wait {
+        let status = command.status()?;
+        if !status.success() {
+            return Err(anyhow!("exited with {}", status));
+        }
+        return Ok(None);
+    }
+
+    // Own process group, so closing the area also reaches the app's children
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+    let child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    Ok(Some(LaunchedProcess {
+        area: area.to_string(),
+        pid: child.id(),
+        program,
+        started: Local::now(),
+        start_ticks: process_start_ticks(child.id()),
+    }))
+}
+
+/// Start time of a process in clock ticks since boot, where `/proc` provides it.
+fn process_start_ticks(pid: u32) -> Option<u64> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // Field 22; the command name before it is in parentheses and may contain spaces
+    let (_, rest) = stat.rsplit_once(')')?;
+    rest.split_whitespace().nth(19)?.parse().ok()
+}
+
+fn load_launched_processes() -> Result<LaunchedProcesses> {
+    let path = processes_file();
+    if !path.exists() {
+        return Ok(LaunchedProcesses::default());
+    }
+    let content = fs::read_to_string(&path)?;
+    toml::from_str(&content).with_context(|| format!("Could not parse {}", path.display()))
+}
+
+/// Caller must hold the data lock.
+fn save_launched_processes(state: &LaunchedProcesses) -> Result<()> {
+    write_atomic(&processes_file(), toml::to_string(state)?)
+}
+
+/// Whether anything from the recorded process group is still running.
+///
+/// If a process with the leader's PID exists, it must have the recorded start
+/// time, so a PID reused by an unrelated program is never treated as ours. The
+/// kernel does not hand out a PID that is still in use as a group id.
+#[cfg(unix)]
+fn is_running(process: &LaunchedProcess) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(process.pid) else { return false };
+    if let (Some(recorded), Some(current)) = (process.start_ticks, process_start_ticks(process.pid)) {
+        if recorded != current {
+            return false;
+        }
+    }
+    // SAFETY: signal 0 only checks that the process group exists
+    unsafe { libc::kill(-pid, 0) == 0 }
+}
+
+#[cfg(not(unix))]
+fn is_running(_process: &LaunchedProcess) -> bool {
+    false
+}
+
+/// SIGTERM each process group, wait up to `grace`, then SIGKILL what is left.
+#[cfg(unix)]
+fn terminate_processes(processes: &[LaunchedProcess], grace: Duration) {
+    let groups: Vec<libc::pid_t> = processes
+        .iter()
+        .filter_map(|p| libc::pid_t::try_from(p.pid).ok())
+        .collect();
+    // SAFETY: plain signal sends to process groups we started
+    let alive = |group: libc::pid_t| unsafe { libc::kill(-group, 0) } == 0;
+    for &group in &groups {
+        unsafe { libc::kill(-group, libc::SIGTERM) };
+    }
+
+    let deadline = Local::now() + grace;
+    while Local::now() < deadline && groups.iter().any(|&g| alive(g)) {
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    for &group in groups.iter().filter(|&&g| alive(g)) {
+        unsafe { libc::kill(-group, libc::SIGKILL) };
+    }
+}
+
+#[cfg(not(unix))]
+fn terminate_processes(_processes: &[LaunchedProcess], _grace: Duration) {
+    eprintln!("Warning: closing apps is only supported on Unix");
+}
+
+/// Close (or offer to close) the apps launched for `area`, following its `close_apps` setting.
+/// Caller must hold the data lock.
+fn close_area_apps(config: &Config, area: &str) -> Result<()> {
+    if !processes_file().exists() {
+        return Ok(());
+    }
+    let mut state = load_launched_processes()?;
+    // Forget processes that have exited on their own
+    state.processes.retain(is_running);
+    let (mine, others): (Vec<_>, Vec<_>) = state.processes.into_iter().partition(|p| p.area == area);
+    state.processes = others;
+
+    let policy = config.area(area).close_apps.unwrap_or(config.close_apps);
+    let close = !mine.is_empty()
+        && match policy {
+            CloseApps::Close => true,
+            CloseApps::Keep => false,
+            CloseApps::Ask => {
+                io::stdin().is_terminal()
+                    && Confirm::with_theme(&ColorfulTheme::default())
+                        .with_prompt(format!("Close {} app(s) launched for '{}'?", mine.len(), area))
+                        .default(true)
+                        .interact()?
+            }
+        };
+
+    if close {
+        let grace = parse_duration(&config.close_grace).unwrap_or_else(|e| {
+            eprintln!("Warning: invalid close_grace in config ({}), using 5s", e);
+            Duration::seconds(5)
+        });
+        terminate_processes(&mine, grace);
+        println!("🛑 Closed {} app(s) from '{}'", mine.len(), area);
+    } else {
+        state.processes.extend(mine);
+    }
+    save_launched_processes(&state)
+}
+
+// ==============================================
+// HOOKS
+// ==============================================
+
+impl HookEvent {
+    fn name(self) -> &'static str {
+        match self {
+            HookEvent::Enter => "on_enter",
+            HookEvent::Leave => "on_leave",
+            HookEvent::SessionStart => "on_session_start",
+            HookEvent::SessionStop => "on_session_stop",
+            HookEvent::NoteAdded => "on_note_added",
+        }
+    }
+
+    fn command(self, hooks: &AreaHooks) -> Option<&str> {
+        match self {
+            HookEvent::Enter => hooks.on_enter.as_deref(),
+            HookEvent::Leave => hooks.on_leave.as_deref(),
+            HookEvent::SessionStart => hooks.on_session_start.as_deref(),
+            HookEvent::SessionStop => hooks.on_session_stop.as_deref(),
+            HookEvent::NoteAdded => hooks.on_note_added.as_deref(),
+        }
+    }
+}
+
+/// Run the global and then the area's hook for `event`.
+///
+/// Every hook gets ICELAND_EVENT, ICELAND_AREA and ICELAND_AREA_DIR plus `env`.
+/// A failure is only an error if the hook's `on_failure` is `abort`.
+/// Never call this with the data lock held: hooks may run iceland commands.
+fn run_hooks(event: HookEvent, area: &str, env: &[(&str, String)]) -> Result<()> {
+    let config = load_config()?;
+    let area_hooks = config.area(area).hooks;
+    for hooks in [&config.hooks, &area_hooks] {
+        let Some(command) = event.command(hooks) else { continue };
+        let timeout = hooks
+            .timeout
+            .as_deref()
+            .or(config.hooks.timeout.as_deref())
+            .unwrap_or(DEFAULT_HOOK_TIMEOUT);
+        let timeout = parse_duration(timeout).unwrap_or_else(|e| {
+            eprintln!("Warning: invalid hook timeout ({}), using {}", e, DEFAULT_HOOK_TIMEOUT);
+            Duration::seconds(10)
+        });
+
+        if let Err(e) = run_hook(command, event, area, env, timeout) {
+            let message = format!("{} hook for '{}' failed: {}", event.name(), area, e);
+            match hooks.on_failure.or(config.hooks.on_failure).unwrap_or_default() {
+                HookFailure::Warn => eprintln!("Warning: {}", message),
+                HookFailure::Abort => return Err(anyhow!(message)),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run_session_stop_hooks(session: &Session) -> Result<()> {
+    let seconds = (session.end - session.start).num_seconds();
+    run_hooks(
+        HookEvent::SessionStop,
+        &session.area,
+        &[("ICELAND_SESSION_SECONDS", seconds.to_string())],
+    )
+}
+
+fn run_hook(command: &str, event: HookEvent, area: &str, env: &[(&str, String)], timeout: Duration) -> Result<()> {
+    let mut command_line = Command::new("sh");
+    command_line
+        .arg("-c")
+        .arg(command)
+        .env("ICELAND_EVENT", event.name())
+        .env("ICELAND_AREA", area)
+        .env("ICELAND_AREA_DIR", area_dir(area))
+        .envs(env.iter().map(|(key, value)| (key, value)))
+        .stdin(Stdio::null());
+    // Own process group, so a timeout also stops whatever the hook started
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command_line, 0);
+    let mut child = command_line.spawn()?;
+
+    let deadline = Local::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return if status.success() {
+                Ok(())
+            } else {
+                Err(anyhow!("exited with {}", status))
+            };
+        }
+        if Local::now() >= deadline {
+            #[cfg(unix)]
+            unsafe {
+                libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+            }
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(anyhow!("timed out after {}s", timeout.num_seconds()));
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+}
+
+// ==============================================
+// STATUS
+// ==============================================
+
+/// Time recorded in an area since the start of `day`, including the running session.
+fn area_time_since(area: &str, day: NaiveDate) -> Result<Duration> {
+    let mut total = Duration::zero();
+    for session in load_sessions()? {
+        if session.area == area && session.start.date_naive() >= day {
+            total += session.end - session.start;
+        }
+    }
+    if let Some(running) = read_running_session()? {
+        if running.area == area && running.start.date_naive() >= day {
+            total += Local::now() - running.start;
+        }
+    }
+    Ok(total)
+}
+
+fn show_goal_progress(area: &str, goals: &AreaGoals) -> Result<()> {
+    let today = Local::now().date_naive();
+    let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+    let targets = [("today", &goals.daily, today), ("this week", &goals.weekly, monday)];
+    for (label, goal, since) in targets {
+        let Some(goal) = goal else { continue };
+        let target = parse_duration(goal)?;
+        let done = area_time_since(area, since)?;
+        let marker = if done >= target { "✅" } else { "🎯" };
+        println!(
+            "{} Goal {}: {} / {} h",
+            marker,
+            label,
+            format_duration(done),
+            format_duration(target)
+        );
+    }
+    Ok(())
+}
+
+fn show_status() -> Result<()> {
+    let current = read_current_area()?;
+    let config = load_config()?;
+    if let Some(profile) = &data_paths().profile {
+        println!("👤 Profile: {}", profile);
+    }
+    match current {
+        Some(area) => {
+            let area_config = config.area(&area);
+            println!("📍 Current area: {}", styled_area_name(&area, &area_config));
+            println!("   Path: {}", area_dir(&area).display());
+
+            match read_running_session()? {
+                Some(running) => {
+                    let elapsed = Local::now() - running.start;
+                    println!(
+                        "⏱️  Session running in '{}' for {} h (since {})",
+                        running.area,
+                        format_duration(elapsed),
+                        running.start.format("%Y-%m-%d %H:%M")
+                    );
+                    let max = config.max_session_length();
+                    if elapsed > max {
+                        println!(
+                            "⚠️  Longer than the {} h limit. Forgot to stop? Use `iceland stop --at <time>` or `--ago <duration>`.",
+                            format_duration(max)
+                        );
+                    }
+                    if let Some(deadline) = running.deadline() {
+                        println!(
+                            "   Timeboxed: {} h planned, {} h left",
+                            format_duration(Duration::seconds(running.planned.unwrap_or(0))),
+                            format_duration(deadline - Local::now())
+                        );
+                    }
+                    if let Some(tag) = &running.tag {
+                        println!("   Tag: {}", tag);
+                    }
+                    if let Some(note) = &running.note {
+                        println!("   Note: {}", note);
+                    }
+                }
+                None => println!("⏸️  No active session."),
+            }
+            show_goal_progress(&area, &area_config.goals)?;
+        }
+        None => {
+            println!("❌ No current area set. Run `init` or `switch`.");
+        }
+    }
+    Ok(())
+}
+
+// ==============================================
+// STATS
+// ==============================================
+
+// Column names of `StatRow`, for CSV output without rows
+const STAT_COLUMNS: [&str; 3] = ["group", "seconds", "percent"];
+
+#[derive(Debug, Serialize)]
+struct StatRow {
+    group: String,
+    seconds: i64,
+    percent: f64,
+}
+
+/// Resolve the date range flags of `stats` into inclusive bounds.
+fn stats_range(
+    since: Option<NaiveDate>,
+    until: Option<NaiveDate>,
+    today: bool,
+    week: bool,
+    month: bool,
+) -> (Option<NaiveDate>, Option<NaiveDate>) {
+    let now = Local::now().date_naive();
+    if today {
+        (Some(now), Some(now))
+    } else if week {
+        let monday = now - Duration::days(now.weekday().num_days_from_monday() as i64);
+        (Some(monday), Some(now))
+    } else if month {
+        (now.with_day(1), Some(now))
+    } else {
+        (since, until)
+    }
+}
+
+fn group_key(session: &Session, group_by: StatsGroup) -> String {
+    match group_by {
+        StatsGroup::Area => session.area.clone(),
+        StatsGroup::Day => session.start.format("%Y-%m-%d").to_string(),
+        StatsGroup::Week => session.start.format("%G-W%V").to_string(),
+        StatsGroup::Month => session.start.format("%Y-%m").to_string(),
+    }
+}
+
+/// Total the sessions that start within `since..=until` per group.
+fn stat_rows(
+    sessions: &[Session],
+    (since, until): (Option<NaiveDate>, Option<NaiveDate>),
+    group_by: StatsGroup,
+) -> Vec<StatRow> {
+    let mut totals: HashMap<String, i64> = HashMap::new();
+    for session in sessions {
+        let day = session.start.date_naive();
+        if since.is_some_and(|d| day < d) || until.is_some_and(|d| day > d) {
+            continue;
+        }
+        let secs = (session.end - session.start).num_seconds();
+        *totals.entry(group_key(session, group_by)).or_insert(0) += secs;
+    }
+
+    let total: i64 = totals.values().sum();
+    let mut rows: Vec<StatRow> = totals
+        .into_iter()
+        .map(|(group, seconds)| StatRow {
+            group,
+            seconds,
+            percent: if total > 0 { seconds as f64 * 100.0 / total as f64 } else { 0.0 },
+        })
+        .collect();
+
+    // Areas by time spent, periods chronologically
+    match group_by {
+        StatsGroup::Area => rows.sort_by(|a, b| b.seconds.cmp(&a.seconds).then(a.group.cmp(&b.group))),
+        _ => rows.sort_by(|a, b| a.group.cmp(&b.group)),
+    }
+    rows
+}
+
+fn show_stats(
+    range: (Option<NaiveDate>, Option<NaiveDate>),
+    group_by: StatsGroup,
+    format: OutputFormat,
+) -> Result<()> {
+    // JSON and CSV stay machine-readable even without data
+    if !sessions_file().exists() && matches!(format, OutputFormat::Table) {
+        println!("No sessions recorded yet.");
+        return Ok(());
+    }
+
+    let rows = stat_rows(&load_sessions()?, range, group_by);
+    let total: i64 = rows.iter().map(|row| row.seconds).sum();
+    match format {
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(&rows)?);
+        }
+        OutputFormat::Csv => {
+            let mut wtr = csv::Writer::from_writer(io::stdout());
+            if rows.is_empty() {
+                wtr.write_record(STAT_COLUMNS)?;
+            }
+            for row in &rows {
+                wtr.serialize(row)?;
+            }
+            wtr.flush()?;
+        }
+        OutputFormat::Table => {
+            if rows.is_empty() {
+                println!("No sessions in the selected range.");
+                return Ok(());
+            }
+            let header = match group_by {
+                StatsGroup::Area => "Area",
+                StatsGroup::Day => "Day",
+                StatsGroup::Week => "Week",
+                StatsGroup::Month => "Month",
+            };
+
+            println!("\n+------------+------------------+---------+");
+            println!("| {:<10} | Time (hh:mm)     | Share   |", header);
+            println!("+------------+------------------+---------+");
+
+            for row in &rows {
+                let hours = row.seconds / 3600;
+                let minutes = (row.seconds % 3600) / 60;
+                println!(
+                    "| {:<10} | {:>7}:{:02} h       | {:>6.1}% |",
+                    row.group, hours, minutes, row.percent
+                );
+            }
+
+            println!("+------------+------------------+---------+");
+            println!(
+                "| {:<10} | {:>7}:{:02} h       | {:>6.1}% |",
+                "Total",
+                total / 3600,
+                (total % 3600) / 60,
+                if total > 0 { 100.0 } else { 0.0 }
+            );
+            println!("+------------+------------------+---------+\n");
+        }
+    }
+    Ok(())
+}
+
+// ==============================================
+// SESSION COMMANDS
+// ==============================================
+
+fn start_session(
+    tag: Option<String>,
+    note: Option<String>,
+    planned: Option<Duration>,
+) -> Result<RunningSession> {
+    let area = read_current_area()?.ok_or_else(|| anyhow!("No current area set"))?;
+    if session_start_file().exists() {
+        return Err(anyhow!("Session already started. Use `stop` first."));
+    }
+    // Before the timer is written, so an aborting hook leaves nothing running
+    run_hooks(HookEvent::SessionStart, &area, &[])?;
+
+    let _lock = lock_data_dir()?;
+    if session_start_file().exists() {
+        return Err(anyhow!("Session already started. Use `stop` first."));
+    }
+    let running = RunningSession {
+        tag,
+        note,
+        planned: planned.map(|d| d.num_seconds()),
+        ..RunningSession::new(&area)
+    };
+    write_running_session(&running)?;
+    println!("▶️  Timer started for area: {}", area);
+    if let Some(deadline) = running.deadline() {
+        println!("   Stops automatically at {}", deadline.format("%H:%M"));
+    }
+    Ok(running)
+}
+
+fn sleep_until(deadline: DateTime<Local>) {
+    if let Ok(wait) = (deadline - Local::now()).to_std() {
+        std::thread::sleep(wait);
+    }
+}
+
+/// Run work/break cycles in the foreground, recording each work block as a session.
+fn run_pomodoro(
+    work: Duration,
+    short_break: Duration,
+    long_break: Duration,
+    cycles: u32,
+    rounds: u32,
+) -> Result<()> {
+    for round in 1..=rounds {
+        println!("\n🍅 Pomodoro {} of {} ({} min)", round, rounds, work.num_minutes());
+        let running = start_session(Some(POMODORO_TAG.to_string()), None, Some(work))?;
+        if let Some(deadline) = running.deadline() {
+            sleep_until(deadline);
+        }
+        let closed = {
+            let _lock = lock_data_dir()?;
+            close_expired_session_locked()?
+        };
+        if let Some(session) = closed {
+            run_session_stop_hooks(&session)?;
+        }
+        print!("\x07");
+
+        if round == rounds {
+            break;
+        }
+        let pause = if round % cycles.max(1) == 0 { long_break } else { short_break };
+        let until = Local::now() + pause;
+        println!("☕ Break until {} ({} min)", until.format("%H:%M"), pause.num_minutes());
+        sleep_until(until);
+        print!("\x07");
+    }
+    println!("\n✅ Pomodoro finished.");
+    Ok(())
+}
+
+fn stop_session(end: Option<DateTime<Local>>) -> Result<()> {
+    let running = read_running_session()?
+        .ok_or_else(|| anyhow!("No active session. Use `start` first."))?;
+    // Ask before locking, so an unanswered prompt doesn't block other commands
+    let end = match end {
+        Some(end) => Some(end),
+        None => resolve_session_end(&running)?,
+    };
+    let stopped = {
+        let _lock = lock_data_dir()?;
+        stop_current_session(running.start, end)?
+    };
+    match stopped {
+        Some(session) => run_session_stop_hooks(&session),
+        None => Ok(()),
+    }
+}
+
+// ==============================================
+// SESSION EDITING
+// ==============================================
+
+fn session_index(sessions: &[Session], id: u64) -> Result<usize> {
+    sessions
+        .iter()
+        .position(|s| s.id == id)
+        .ok_or_else(|| anyhow!("No session with id {}. See `session list --ids`.", id))
+}
+
+/// Reject a session that ends before it starts or overlaps any other session.
+fn validate_session(sessions: &[Session], session: &Session) -> Result<()> {
+    if session.end <= session.start {
+        return Err(anyhow!("Session would end before it starts"));
+    }
+    if session.end > Local::now() {
+        return Err(anyhow!("Session would end in the future ({})", session.end.format("%Y-%m-%d %H:%M")));
+    }
+    let clash = sessions
+        .iter()
+        .find(|s| s.id != session.id && s.start < session.end && session.start < s.end);
+    if let Some(other) = clash {
+        return Err(anyhow!(
+            "Session would overlap session {} ({} {} - {})",
+            other.id,
+            other.area,
+            other.start.format("%Y-%m-%d %H:%M"),
+            other.end.format("%Y-%m-%d %H:%M")
+        ));
+    }
+    Ok(())
+}
+
+fn manage_sessions(action: SessionAction) -> Result<()> {
+    if let SessionAction::List { ids, area } = action {
+        return show_history(area, ids);
+    }
+
+    let _lock = lock_data_dir()?;
+    let mut sessions = load_sessions_for_edit()?;
+
+    match action {
+        SessionAction::List { .. } => unreachable!(),
+        SessionAction::Edit { id, start, end, area } => {
+            let i = session_index(&sessions, id)?;
+            if let Some(area) = &area {
+                if !area_exists(area) {
+                    return Err(anyhow!("Area '{}' does not exist.", area));
+                }
+            }
+            // Like `log`, a bare HH:MM refers to the session's day, not the most recent one
+            let start = start
+                .map(|s| parse_time_on(&s, Some(sessions[i].start.date_naive())))
+                .transpose()?;
+            let end = end
+                .map(|e| parse_time_on(&e, Some(sessions[i].end.date_naive())))
+                .transpose()?;
+            let edited = Session {
+                area: area.unwrap_or_else(|| sessions[i].area.clone()),
+                start: start.unwrap_or(sessions[i].start),
+                end: end.unwrap_or(sessions[i].end),
+                ..sessions[i].clone()
+            };
+            validate_session(&sessions, &edited)?;
+            sessions[i] = edited;
+            println!("✏️  Session {} updated", id);
+        }
+        SessionAction::Delete { id } => {
+            let i = session_index(&sessions, id)?;
+            let removed = sessions.remove(i);
+            println!(
+                "🗑️  Deleted session {} ({} {} - {})",
+                id,
+                removed.area,
+                removed.start.format("%Y-%m-%d %H:%M"),
+                removed.end.format("%Y-%m-%d %H:%M")
+            );
+        }
+        SessionAction::Split { id, at } => {
+            let second = split_session(&mut sessions, id, &at)?;
+            println!("✂️  Split session {} into {} and {}", id, id, second);
+        }
+        SessionAction::Merge { first, second } => {
+            let (kept, dropped) = merge_sessions(&mut sessions, first, second)?;
+            println!("🔗 Merged session {} into {}", dropped, kept);
+        }
+    }
+
+    save_sessions(&sessions)
+}
+
+/// Split session `id` at `at` and return the id of the second half.
+fn split_session(sessions: &mut Vec<Session>, id: u64, at: &str) -> Result<u64> {
+    let i = session_index(sessions, id)?;
+    // A bare HH:MM is on the day the session started, or the day it ended if it ran past midnight
+    let inside = |t: &DateTime<Local>| *t > sessions[i].start && *t < sessions[i].end;
+    let bare_time = NaiveTime::parse_from_str(at.trim(), "%H:%M").is_ok();
+    let mut at_time = parse_time_on(at, Some(sessions[i].start.date_naive()))?;
+    if bare_time && !inside(&at_time) {
+        at_time = parse_time_on(at, Some(sessions[i].end.date_naive()))?;
+    }
+    let at = at_time;
+    if !inside(&at) {
+        return Err(anyhow!("Split time must be inside session {}", id));
+    }
+    let second = Session {
+        start: at,
+        planned: None,
+        id: next_session_id(sessions),
+        ..sessions[i].clone()
+    };
+    let second_id = second.id;
+    sessions[i].end = at;
+    sessions.insert(i + 1, second);
+    Ok(second_id)
+}
+
+/// Merge two sessions of one area into the earlier one; returns (kept id, dropped id).
+fn merge_sessions(sessions: &mut Vec<Session>, first: u64, second: u64) -> Result<(u64, u64)> {
+    if first == second {
+        return Err(anyhow!("Cannot merge a session with itself"));
+    }
+    let a = session_index(sessions, first)?;
+    let b = session_index(sessions, second)?;
+    if sessions[a].area != sessions[b].area {
+        return Err(anyhow!(
+            "Sessions {} and {} belong to different areas ('{}' and '{}')",
+            first,
+            second,
+            sessions[a].area,
+            sessions[b].area
+        ));
+    }
+    // The earlier session keeps its id, tag and note
+    let (keep, drop) = if sessions[a].start <= sessions[b].start { (a, b) } else { (b, a) };
+    let merged = Session {
+        start: sessions[keep].start.min(sessions[drop].start),
+        end: sessions[keep].end.max(sessions[drop].end),
+        planned: None,
+        ..sessions[keep].clone()
+    };
+    let dropped_id = sessions[drop].id;
+    let others: Vec<_> = sessions.iter().filter(|s| s.id != dropped_id).cloned().collect();
+    validate_session(&others, &merged)?;
+    let kept_id = merged.id;
+    sessions[keep] = merged;
+    sessions.remove(drop);
+    Ok((kept_id, dropped_id))
+}
+
+/// First gap of `length` on `day` from 09:00 that no recorded session overlaps.
+fn first_free_slot(sessions: &[Session], day: NaiveDate, length: Duration) -> Result<DateTime<Local>> {
+    let mut start = parse_time_on("09:00", Some(day))?;
+    let mut sorted: Vec<_> = sessions.iter().collect();
+    sorted.sort_by_key(|s| s.start);
+    for s in sorted {
+        if s.start < start + length && start < s.end {
+            start = s.end;
+        }
+    }
+    Ok(start)
+}
+
+fn log_session(
+    area: &str,
+    start: Option<String>,
+    end: Option<String>,
+    duration: Option<Duration>,
+    date: Option<NaiveDate>,
+    tag: Option<String>,
+    note: Option<String>,
+) -> Result<()> {
+    if !area_exists(area) {
+        return Err(anyhow!("Area '{}' does not exist. Use `add-area` first.", area));
+    }
+    let _lock = lock_data_dir()?;
+    let sessions = load_sessions()?;
+
+    let start = start.map(|s| parse_time_on(&s, date)).transpose()?;
+    let end = end.map(|e| parse_time_on(&e, date)).transpose()?;
+    let (start, end) = match (start, end, duration) {
+        (Some(start), Some(end), None) => (start, end),
+        (Some(start), None, Some(length)) => (start, start + length),
+        (None, Some(end), Some(length)) => (end - length, end),
+        (None, None, Some(length)) => match date {
+            Some(day) if day != Local::now().date_naive() => {
+                let start = first_free_slot(&sessions, day, length)?;
+                (start, start + length)
+            }
+            _ => {
+                let end = Local::now();
+                (end - length, end)
+            }
+        },
+        (Some(_), Some(_), Some(_)) => {
+            return Err(anyhow!("Give at most two of --start, --end and --duration"))
+        }
+        _ => return Err(anyhow!("Give --start and --end, or --duration")),
+    };
+
+    if end > Local::now() {
+        return Err(anyhow!("Session would end in the future ({})", end.format("%Y-%m-%d %H:%M")));
+    }
+    let session = Session {
+        area: area.to_string(),
+        start,
+        end,
+        tag,
+        note,
+        planned: None,
+        id: 0,
+    };
+    validate_session(&sessions, &session)?;
+    record_session(&session)?;
+
+    println!(
+        "📒 Logged {} h in '{}' ({} - {})",
+        format_duration(end - start),
+        area,
+        start.format("%Y-%m-%d %H:%M"),
+        end.format("%H:%M")
+    );
+    Ok(())
+}
+
+// ==============================================
+// DESTROY
+// ==============================================
+
+fn destroy_in_area(area: &str, target: DestroyTarget, hard: bool) -> Result<()> {
+    let area_path = area_dir(area);
+    if !area_path.exists() {
+        return Err(anyhow!("Area '{}' does not exist.", area));
+    }
+    let _lock = lock_data_dir()?;
+    auto_snapshot("destroy")?;
+
+    let dirs: Vec<PathBuf> = match target {
+        DestroyTarget::Browser => Browser::MANAGED
+            .iter()
+            .map(|browser| area_path.join(browser.profile_dir()))
+            .filter(|dir| dir.exists())
+            .collect(),
+        DestroyTarget::Notes => {
+            let notes = area_path.join("notes");
+            if !notes.exists() {
+                println!("No notes folder found for {}", area);
+                return Ok(());
+            }
+            vec![notes]
+        }
+    };
+    if dirs.is_empty() {
+        println!("No browser profiles found for {}", area);
+        return Ok(());
+    }
+
+    let trashed = if hard {
+        for dir in &dirs {
+            fs::remove_dir_all(dir)?;
+        }
+        None
+    } else {
+        let what = match target {
+            DestroyTarget::Browser => "browser",
+            DestroyTarget::Notes => "notes",
+        };
+        Some(move_to_trash(&format!("destroy {} {}", area, what), &dirs, None)?)
+    };
+
+    for dir in &dirs {
+        match target {
+            DestroyTarget::Browser => {
+                let browser = Browser::MANAGED
+                    .iter()
+                    .find(|b| dir.ends_with(b.profile_dir()))
+                    .copied()
+                    .unwrap_or_default();
+                // Left empty so `restore` can put the old profile back; the next launch seeds it
+                fs::create_dir_all(dir)?;
+                println!("♻️  Reset {:?} profile: {}", browser, browser.profile_dir())
+            }
+            DestroyTarget::Notes => {
+                fs::create_dir_all(dir)?;
+                println!("♻️  Reset notes for {}", area)
+            }
+        }
+    }
+    if let Some(id) = trashed {
+        println!("Old content moved to the trash. Undo with `iceland restore {}`.", id);
+    }
+    Ok(())
+}
+
+// ==============================================
+// TRASH
+// ==============================================
+
+fn trash_entry_dir(id: &str) -> PathBuf {
+    trash_dir().join(id)
+}
+
+/// Move `paths` (inside the iceland dir) into a new trash entry and return its id.
+/// Caller must hold the data lock.
+fn move_to_trash(description: &str, paths: &[PathBuf], area: Option<(&str, AreaConfig)>) -> Result<String> {
+    let now = Local::now();
+    let base = now.format("%Y%m%d-%H%M%S").to_string();
+    let mut id = base.clone();
+    let mut n = 1;
+    while trash_entry_dir(&id).exists() {
+        n += 1;
+        id = format!("{}-{}", base, n);
+    }
+    let entry = trash_entry_dir(&id);
+    fs::create_dir_all(&entry)?;
+
+    let mut items = Vec::new();
+    for path in paths {
+        let relative = path
+            .strip_prefix(iceland_dir())
+            .with_context(|| format!("{} is outside the iceland directory", path.display()))?
+            .to_path_buf();
+        let stored = entry.join(&relative);
+        if let Some(parent) = stored.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::rename(path, &stored).with_context(|| format!("Could not move {} to the trash", path.display()))?;
+        items.push(relative);
+    }
+
+    let (area, area_config) = match area {
+        Some((name, config)) => (Some(name.to_string()), Some(config)),
+        None => (None, None),
+    };
+    let manifest = TrashManifest {
+        created: now,
+        description: description.to_string(),
+        items,
+        area,
+        area_config,
+    };
+    write_atomic(&entry.join(TRASH_MANIFEST), toml::to_string_pretty(&manifest)?)?;
+    Ok(id)
+}
+
+/// All readable trash entries, newest first.
+fn load_trash() -> Result<Vec<(String, TrashManifest)>> {
+    let mut entries = Vec::new();
+    if !trash_dir().is_dir() {
+        return Ok(entries);
+    }
+    for entry in fs::read_dir(trash_dir())? {
+        let path = entry?.path();
+        let Some(id) = path.file_name().and_then(|n| n.to_str()).map(str::to_string) else { continue };
+        let Ok(content) = fs::read_to_string(path.join(TRASH_MANIFEST)) else { continue };
+        match toml::from_str::<TrashManifest>(&content) {
+            Ok(manifest) => entries.push((id, manifest)),
+            Err(e) => eprintln!("Warning: skipping trash entry '{}': {}", id, e),
+        }
+    }
+    entries.sort_by_key(|(_, m)| Reverse(m.created));
+    Ok(entries)
+}
+
+fn manage_trash(action: TrashAction) -> Result<()> {
+    match action {
+        TrashAction::List => {
+            let entries = load_trash()?;
+            if entries.is_empty() {
+                println!("Trash is empty.");
+                return Ok(());
+            }
+            for (id, manifest) in entries {
+                println!(
+                    "{}  {}  {}",
+                    id,
+                    manifest.created.format("%Y-%m-%d %H:%M"),
+                    manifest.description
+                );
+            }
+        }
+        TrashAction::Purge { older_than } => {
+            let _lock = lock_data_dir()?;
+            let cutoff = older_than.map(|age| Local::now() - age);
+            let mut purged = 0;
+            for (id, manifest) in load_trash()? {
+                if cutoff.is_some_and(|cutoff| manifest.created >= cutoff) {
+                    continue;
+                }
+                fs::remove_dir_all(trash_entry_dir(&id))?;
+                purged += 1;
+            }
+            println!("🗑️  Purged {} trash entries.", purged);
+        }
+    }
+    Ok(())
+}
+
+fn restore_from_trash(id: &str) -> Result<()> {
+    let _lock = lock_data_dir()?;
+    let entry = trash_entry_dir(id);
+    let content = fs::read_to_string(entry.join(TRASH_MANIFEST))
+        .map_err(|_| anyhow!("No trash entry '{}'. See `iceland trash list`.", id))?;
+    let manifest: TrashManifest = toml::from_str(&content)?;
+
+    let mut config = load_config()?;
+    if let Some(area) = &manifest.area {
+        if config.areas.contains_key(area) {
+            return Err(anyhow!("Area '{}' exists again. Rename or remove it first.", area));
+        }
+    }
+
+    // Destroy recreates empty directories; anything else in the way is a conflict
+    for item in &manifest.items {
+        let target = iceland_dir().join(item);
+        let empty_dir = target.is_dir() && fs::read_dir(&target)?.next().is_none();
+        if target.exists() && !empty_dir {
+            return Err(anyhow!("{} already has content; not overwriting it.", target.display()));
+        }
+    }
+
+    for item in &manifest.items {
+        let target = iceland_dir().join(item);
+        if target.is_dir() {
+            fs::remove_dir(&target)?;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::rename(entry.join(item), &target)?;
+    }
+
+    if let Some(area) = manifest.area {
+        config.areas.insert(area, manifest.area_config.unwrap_or_default());
+        save_config(&config)?;
+    }
+    fs::remove_dir_all(&entry)?;
+    println!("♻️  Restored: {}", manifest.description);
+    Ok(())
+}
+
+// ==============================================
+// DOCTOR
+// ==============================================
+
+impl Problem {
+    fn describe(&self) -> String {
+        match self {
+            Problem::MissingDir(area) => format!("Area '{}' is in config.toml but has no directory", area),
+            Problem::OrphanDir(name) => format!("Directory '{}' is not an area in config.toml", name),
+            Problem::DanglingCurrentArea(area) => format!("current_area points to unknown area '{}'", area),
+            Problem::StaleRunningSession(reason) => format!("Running session is stale: {}", reason),
+            Problem::UnparseableSession { line, error } => {
+                format!("sessions.csv line {} cannot be parsed: {}", line, error)
+            }
+            Problem::NegativeSession(id) => format!("Session #{} ends before it starts", id),
+            Problem::OverlappingSessions(a, b) => format!("Sessions #{} and #{} overlap", a, b),
+            Problem::MalformedCards { deck, lines } => format!(
+                "{}: line(s) {} are not 'front|back'",
+                deck.strip_prefix(iceland_dir()).unwrap_or(deck).display(),
+                lines.iter().map(|l| l.to_string()).collect::<Vec<_>>().join(", ")
+            ),
+        }
+    }
+}
+
+/// A sessions.csv row that does not parse as a `Session`.
+struct BrokenRow {
+    line: u64,
+    record: csv::StringRecord,
+    error: String,
+}
+
+/// Every row of sessions.csv: the sessions that parse and the rows that don't.
+fn scan_sessions() -> Result<(Vec<Session>, Vec<BrokenRow>)> {
+    let mut sessions = Vec::new();
+    let mut broken = Vec::new();
+    if !sessions_file().exists() {
+        return Ok((sessions, broken));
+    }
+    let mut rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_path(sessions_file())?;
+    let headers = rdr.headers()?.clone();
+    for result in rdr.records() {
+        let record = result?;
+        let line = record.position().map_or(0, |p| p.line());
+        match record.deserialize::<Session>(Some(&headers)) {
+            Ok(session) => sessions.push(session),
+            Err(e) => broken.push(BrokenRow {
+                line,
+                record,
+                error: e.to_string(),
+            }),
+        }
+    }
+    assign_missing_ids(&mut sessions);
+    Ok((sessions, broken))
+}
+
+/// Pairs of sessions (by id) whose time ranges overlap, ignoring ones that end before they start.
+fn overlapping_sessions(sessions: &[Session]) -> Vec<(u64, u64)> {
+    let mut sorted: Vec<&Session> = sessions.iter().filter(|s| s.end > s.start).collect();
+    sorted.sort_by_key(|s| s.start);
+    let mut overlaps = Vec::new();
+    for (i, a) in sorted.iter().enumerate() {
+        for b in sorted[i + 1..].iter().take_while(|b| b.start < a.end) {
+            overlaps.push((a.id, b.id));
+        }
+    }
+    overlaps
+}
+
+/// Line numbers of a deck that are neither blank nor `front|back`.
+fn malformed_card_lines(deck: &Path) -> Result<Vec<usize>> {
+    let content = fs::read_to_string(deck)?;
+    Ok(content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            !line.trim().is_empty() && line.split_once('|').is_none_or(|(front, _)| front.trim().is_empty())
+        })
+        .map(|(i, _)| i + 1)
+        .collect())
+}
+
+fn deck_files(area: &str) -> Result<Vec<PathBuf>> {
+    let dir = area_dir(area).join("flashcards");
+    let mut decks = Vec::new();
+    if dir.is_dir() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            let hidden = path.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with('.'));
+            if path.is_file() && !hidden {
+                decks.push(path);
+            }
+        }
+    }
+    decks.sort();
+    Ok(decks)
+}
+
+fn find_problems() -> Result<Vec<Problem>> {
+    let config = load_config()?;
+    let mut problems = Vec::new();
+
+    for area in config.areas.keys() {
+        if !area_dir(area).is_dir() {
+            problems.push(Problem::MissingDir(area.clone()));
+        }
+    }
+    let mut orphans = Vec::new();
+    for entry in fs::read_dir(iceland_dir())? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else { continue };
+        if path.is_dir() && !name.starts_with('.') && name != TEMPLATES_DIR && !config.areas.contains_key(name) {
+            orphans.push(name.to_string());
+        }
+    }
+    orphans.sort();
+    problems.extend(orphans.into_iter().map(Problem::OrphanDir));
+
+    if let Some(current) = read_current_area()? {
+        if !config.areas.contains_key(&current) {
+            problems.push(Problem::DanglingCurrentArea(current));
+        }
+    }
+
+    match read_running_session() {
+        Err(e) => problems.push(Problem::StaleRunningSession(format!("unreadable ({})", e))),
+        Ok(Some(running)) => {
+            let elapsed = Local::now() - running.start;
+            if !config.areas.contains_key(&running.area) {
+                problems.push(Problem::StaleRunningSession(format!("unknown area '{}'", running.area)));
+            } else if elapsed < Duration::zero() {
+                problems.push(Problem::StaleRunningSession("started in the future".to_string()));
+            } else if elapsed > config.max_session_length() {
+                problems.push(Problem::StaleRunningSession(format!(
+                    "running for {} h (max_session is {})",
+                    format_duration(elapsed),
+                    config.max_session
+                )));
+            }
+        }
+        Ok(None) => {}
+    }
+
+    let (sessions, broken) = scan_sessions()?;
+    for row in broken {
+        problems.push(Problem::UnparseableSession {
+            line: row.line,
+            error: row.error,
+        });
+    }
+    for session in sessions.iter().filter(|s| s.end <= s.start) {
+        problems.push(Problem::NegativeSession(session.id));
+    }
+    for (a, b) in overlapping_sessions(&sessions) {
+        problems.push(Problem::OverlappingSessions(a, b));
+    }
+
+    for area in config.areas.keys() {
+        for deck in deck_files(area)? {
+            let lines = malformed_card_lines(&deck)?;
+            if !lines.is_empty() {
+                problems.push(Problem::MalformedCards { deck, lines });
+            }
+        }
+    }
+    Ok(problems)
+}
+
+fn run_doctor(fix: bool) -> Result<()> {
+    if !config_path().exists() {
+        return Err(anyhow!("Iceland is not initialized. Run `iceland init` first."));
+    }
+    if !fix {
+        let problems = find_problems()?;
+        if problems.is_empty() {
+            println!("✅ No problems found.");
+            return Ok(());
+        }
+        for problem in &problems {
+            println!("✗ {}", problem.describe());
+        }
+        println!("{} problem(s) found. Run `iceland doctor --fix` to repair them.", problems.len());
+        return Ok(());
+    }
+
+    if !io::stdin().is_terminal() {
+        return Err(anyhow!("`doctor --fix` asks how to repair each problem; run it in a terminal."));
+    }
+    let _lock = lock_data_dir()?;
+    let problems = find_problems()?;
+    if problems.is_empty() {
+        println!("✅ No problems found.");
+        return Ok(());
+    }
+    auto_snapshot("doctor")?;
+
+    let mut config = load_config()?;
+    let (mut sessions, broken) = scan_sessions()?;
+    let mut kept_broken = Vec::new();
+    let mut sessions_changed = false;
+    let mut broken = broken.into_iter();
+    let mut fixed = 0;
+
+    for problem in &problems {
+        let description = problem.describe();
+        let choice = |options: &[&str]| -> Result<usize> {
+            Ok(Select::with_theme(&ColorfulTheme::default())
+                .with_prompt(&description)
+                .items(options)
+                .default(0)
+                .interact()?)
+        };
+        let before = fixed;
+        match problem {
+            Problem::MissingDir(area) => match choice(&["Recreate the directory", "Remove the area from config", "Skip"])? {
+                0 => {
+                    create_area_structure(area, &load_template(&default_template_for(area))?)?;
+                    fixed += 1;
+                }
+                1 => {
+                    config.areas.shift_remove(area);
+                    save_config(&config)?;
+                    fixed += 1;
+                }
+                _ => {}
+            },
+            Problem::OrphanDir(name) => match choice(&["Add it as an area", "Move it to the trash", "Skip"])? {
+                0 => {
+                    validate_area_name(name)?;
+                    config.areas.insert(name.clone(), AreaConfig::default());
+                    save_config(&config)?;
+                    fixed += 1;
+                }
+                1 => {
+                    move_to_trash(&format!("doctor: orphan directory {}", name), &[area_dir(name)], None)?;
+                    fixed += 1;
+                }
+                _ => {}
+            },
+            Problem::DanglingCurrentArea(_) => {
+                let first = config.areas.keys().next().cloned();
+                let switch = first.as_ref().map(|a| format!("Set it to '{}'", a));
+                let mut options = vec!["Clear it"];
+                options.extend(switch.as_deref());
+                options.push("Skip");
+                match options[choice(&options)?] {
+                    "Clear it" => {
+                        fs::remove_file(current_area_file())?;
+                        fixed += 1;
+                    }
+                    "Skip" => {}
+                    _ => {
+                        if let Some(first) = &first {
+                            write_current_area(first)?;
+                            fixed += 1;
+                        }
+                    }
+                }
+            }
+            Problem::StaleRunningSession(_) => {
+                let known = read_running_session()
+                    .ok()
+                    .flatten()
+                    .filter(|r| config.areas.contains_key(&r.area) && r.start < Local::now());
+                let mut options = vec!["Discard it"];
+                if known.is_some() {
+                    options.insert(0, "Record it, capped at max_session");
+                }
+                options.push("Skip");
+                match options[choice(&options)?] {
+                    "Discard it" => {
+                        fs::remove_file(session_start_file())?;
+                        fixed += 1;
+                    }
+                    "Skip" => {}
+                    _ => {
+                        if let Some(running) = known {
+                            let end = (running.start + config.max_session_length()).min(Local::now());
+                            record_session(&running.finish(end))?;
+                            fs::remove_file(session_start_file())?;
+                            // record_session wrote the file; pick up the new row
+                            (sessions, _) = scan_sessions()?;
+                            fixed += 1;
+                        }
+                    }
+                }
+            }
+            Problem::UnparseableSession { .. } => {
+                let Some(row) = broken.next() else { continue };
+                if choice(&["Drop the row", "Keep it"])? == 0 {
+                    println!("Dropped: {}", row.record.iter().collect::<Vec<_>>().join(","));
+                    sessions_changed = true;
+                    fixed += 1;
+                } else {
+                    kept_broken.push(row.record);
+                }
+            }
+            Problem::NegativeSession(id) => {
+                let Ok(i) = session_index(&sessions, *id) else { continue };
+                match choice(&["Swap start and end", "Delete the session", "Skip"])? {
+                    0 => {
+                        let session = &mut sessions[i];
+                        std::mem::swap(&mut session.start, &mut session.end);
+                        sessions_changed = true;
+                        fixed += 1;
+                    }
+                    1 => {
+                        sessions.remove(i);
+                        sessions_changed = true;
+                        fixed += 1;
+                    }
+                    _ => {}
+                }
+            }
+            Problem::OverlappingSessions(a, b) => {
+                // An earlier fix may already have resolved this pair
+                let (Ok(i), Ok(j)) = (session_index(&sessions, *a), session_index(&sessions, *b)) else { continue };
+                if sessions[j].start >= sessions[i].end {
+                    continue;
+                }
+                let trim = format!("End #{} when #{} starts", a, b);
+                let delete_a = format!("Delete #{}", a);
+                let delete_b = format!("Delete #{}", b);
+                match choice(&[&trim, &delete_a, &delete_b, "Skip"])? {
+                    0 => {
+                        sessions[i].end = sessions[j].start;
+                        if sessions[i].end <= sessions[i].start {
+                            sessions.remove(i);
+                        }
+                        sessions_changed = true;
+                        fixed += 1;
+                    }
+                    1 => {
+                        sessions.remove(i);
+                        sessions_changed = true;
+                        fixed += 1;
+                    }
+                    2 => {
+                        sessions.remove(j);
+                        sessions_changed = true;
+                        fixed += 1;
+                    }
+                    _ => {}
+                }
+            }
+            Problem::MalformedCards { deck, lines } => {
+                if choice(&["Remove those lines", "Skip"])? == 0 {
+                    let content = fs::read_to_string(deck)?;
+                    let kept: String = content
+                        .lines()
+                        .enumerate()
+                        .filter(|(i, _)| !lines.contains(&(i + 1)))
+                        .map(|(_, line)| format!("{}\n", line))
+                        .collect();
+                    write_atomic(deck, kept)?;
+                    fixed += 1;
+                }
+            }
+        }
+        if fixed > before {
+            println!("  ✔ fixed");
+        }
+    }
+
+    if sessions_changed {
+        let mut wtr = csv::WriterBuilder::new()
+            .flexible(true)
+            .from_writer(serialize_sessions(&sessions)?);
+        for record in &kept_broken {
+            wtr.write_record(record)?;
+        }
+        write_atomic(&sessions_file(), wtr.into_inner().map_err(|e| anyhow!("{}", e))?)?;
+    }
+
+    println!("🩺 Fixed {} of {} problem(s).", fixed, problems.len());
+    Ok(())
+}
+
+// ==============================================
+// BACKUP & RESTORE
+// ==============================================
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Entries to back up as (archive path, path on disk): the config plus the
+/// data dir. Directories are included so empty area folders survive a restore.
+fn backup_files() -> Result<Vec<(String, PathBuf)>> {
+    let mut files = Vec::new();
+    if config_path().exists() {
+        files.push((CONFIG_FILE.to_string(), config_path()));
+    }
+
+    let root = iceland_dir();
+    let walker = WalkDir::new(&root)
+        .min_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| e.depth() > 1 || !BACKUP_EXCLUDE.iter().any(|name| e.file_name() == *name));
+    for entry in walker {
+        let entry = entry?;
+        let file_type = entry.file_type();
+        if !(file_type.is_file() || file_type.is_dir()) || entry.path() == config_path() {
+            continue;
+        }
+        let relative: Vec<_> = entry
+            .path()
+            .strip_prefix(&root)?
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        files.push((format!("data/{}", relative.join("/")), entry.path().to_path_buf()));
+    }
+    Ok(files)
+}
+
+/// Write a zstd-compressed tar of the whole tree, manifest first.
+/// Caller must hold the data lock.
+fn write_backup(out: &Path) -> Result<BackupManifest> {
+    let files = backup_files()?;
+    let mut checksums = IndexMap::new();
+    for (name, path) in files.iter().filter(|(_, path)| path.is_file()) {
+        checksums.insert(name.clone(), sha256_hex(&fs::read(path)?));
+    }
+    let manifest = BackupManifest {
+        schema_version: SCHEMA_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created: Local::now(),
+        areas: load_config()?.areas.keys().cloned().collect(),
+        files: checksums,
+    };
+
+    if let Some(parent) = out.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    let partial = out.with_extension("partial");
+    {
+        let encoder = zstd::Encoder::new(File::create(&partial)?, 0)?;
+        let mut archive = tar::Builder::new(encoder);
+
+        let manifest_toml = toml::to_string_pretty(&manifest)?;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(manifest_toml.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(manifest.created.timestamp().max(0) as u64);
+        header.set_cksum();
+        archive.append_data(&mut header, BACKUP_MANIFEST, manifest_toml.as_bytes())?;
+
+        for (name, path) in &files {
+            if path.is_dir() {
+                archive.append_dir(name, path)?;
+            } else {
+                archive.append_path_with_name(path, name)?;
+            }
+        }
+        archive.into_inner()?.finish()?.sync_all()?;
+    }
+    fs::rename(&partial, out).with_context(|| format!("Could not write {}", out.display()))?;
+    Ok(manifest)
+}
+
+fn backup(out: Option<PathBuf>) -> Result<()> {
+    let _lock = lock_data_dir()?;
+    let out = out.unwrap_or_else(|| PathBuf::from(format!("iceland-{}.tar.zst", Local::now().format("%Y%m%d-%H%M%S"))));
+    let manifest = write_backup(&out)?;
+    println!(
+        "💾 Backed up {} areas ({} files) to {}",
+        manifest.areas.len(),
+        manifest.files.len(),
+        out.display()
+    );
+    Ok(())
+}
+
+/// Snapshot the tree into `.snapshots`, keeping only the newest `keep`.
+/// Caller must hold the data lock.
+fn take_snapshot(reason: &str, keep: usize) -> Result<Option<PathBuf>> {
+    if keep == 0 {
+        return Ok(None);
+    }
+    let path = snapshots_dir().join(format!("{}-{}.tar.zst", Local::now().format("%Y%m%d-%H%M%S"), reason));
+    write_backup(&path)?;
+
+    let mut snapshots: Vec<PathBuf> = fs::read_dir(snapshots_dir())?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.extension() == Some("zst".as_ref()))
+        .collect();
+    snapshots.sort();
+    let excess = snapshots.len().saturating_sub(keep);
+    for old in &snapshots[..excess] {
+        fs::remove_file(old)?;
+    }
+    Ok(Some(path))
+}
+
+/// The snapshot taken before destructive commands when `snapshots` is set in the config.
+fn auto_snapshot(reason: &str) -> Result<()> {
+    take_snapshot(reason, load_config()?.snapshots)?;
+    Ok(())
+}
+
+/// What `extract_backup` found: the manifest, config and sessions as text,
+/// and the data files (relative to the data dir) it extracted.
+#[derive(Debug)]
+struct ExtractedBackup {
+    manifest: BackupManifest,
+    config: Option<String>,
+    sessions: Option<String>,
+    files: Vec<PathBuf>,
+}
+
+/// Read only the manifest, which is always the first entry of a backup.
+fn read_backup_manifest(archive_path: &Path) -> Result<BackupManifest> {
+    let file = File::open(archive_path).with_context(|| format!("Could not open {}", archive_path.display()))?;
+    let mut archive = tar::Archive::new(zstd::Decoder::new(file)?);
+    let not_a_backup = || anyhow!("{} is not an iceland backup (no manifest)", archive_path.display());
+    let unreadable = || format!("Could not read {} as a backup archive", archive_path.display());
+
+    let mut entry = archive
+        .entries()
+        .with_context(unreadable)?
+        .next()
+        .ok_or_else(not_a_backup)?
+        .with_context(unreadable)?;
+    if entry.path()?.to_string_lossy() != BACKUP_MANIFEST {
+        return Err(not_a_backup());
+    }
+    let mut content = String::new();
+    entry.read_to_string(&mut content).with_context(unreadable)?;
+    toml::from_str(&content).context("Backup manifest is unreadable")
+}
+
+/// Verify every checksum in `archive_path` and, given `staging`, unpack the
+/// data files (only `area`'s, if set) there.
+fn extract_backup(archive_path: &Path, area: Option<&str>, staging: Option<&Path>) -> Result<ExtractedBackup> {
+    let file = File::open(archive_path).with_context(|| format!("Could not open {}", archive_path.display()))?;
+    let mut archive = tar::Archive::new(zstd::Decoder::new(file)?);
+    let not_a_backup = || anyhow!("{} is not an iceland backup (no manifest)", archive_path.display());
+
+    let mut manifest: Option<BackupManifest> = None;
+    let mut config = None;
+    let mut sessions = None;
+    let mut files = Vec::new();
+    let mut verified = 0;
+    let unreadable = || format!("Could not read {} as a backup archive", archive_path.display());
+    for entry in archive.entries().with_context(unreadable)? {
+        let mut entry = entry.with_context(unreadable)?;
+        let is_dir = entry.header().entry_type().is_dir();
+        let name = entry.path()?.to_string_lossy().trim_end_matches('/').to_string();
+        let mut bytes = Vec::new();
+        entry.read_to_end(&mut bytes).with_context(unreadable)?;
+
+        let Some(manifest) = &manifest else {
+            if name != BACKUP_MANIFEST {
+                return Err(not_a_backup());
+            }
+            manifest = Some(toml::from_str(std::str::from_utf8(&bytes)?).context("Backup manifest is unreadable")?);
+            continue;
+        };
+        if !is_dir {
+            let expected = manifest
+                .files
+                .get(&name)
+                .ok_or_else(|| anyhow!("{} in the archive is not listed in its manifest", name))?;
+            if sha256_hex(&bytes) != *expected {
+                return Err(anyhow!("Checksum mismatch for {}; the archive is damaged.", name));
+            }
+            verified += 1;
+        }
+
+        if name == CONFIG_FILE {
+            config = Some(String::from_utf8(bytes)?);
+            continue;
+        }
+        let Some(relative) = name.strip_prefix("data/") else { continue };
+        if relative == SESSIONS_FILE {
+            sessions = Some(String::from_utf8_lossy(&bytes).into_owned());
+        }
+        if area.is_some_and(|area| relative != area && !relative.starts_with(&format!("{}/", area))) {
+            continue;
+        }
+
+        // Paths come from the archive; refuse anything that could land outside the staging dir
+        let relative = PathBuf::from(relative);
+        if relative.components().any(|c| !matches!(c, Component::Normal(_))) {
+            return Err(anyhow!("Refusing unsafe path {} in the archive.", name));
+        }
+        if is_dir {
+            if let Some(staging) = staging {
+                fs::create_dir_all(staging.join(&relative))?;
+            }
+            continue;
+        }
+        if let Some(staging) = staging {
+            let dest = staging.join(&relative);
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&dest, &bytes)?;
+        }
+        files.push(relative);
+    }
+
+    let manifest = manifest.ok_or_else(not_a_backup)?;
+    if verified != manifest.files.len() {
+        return Err(anyhow!(
+            "Archive is incomplete: {} of {} files present.",
+            verified,
+            manifest.files.len()
+        ));
+    }
+    if manifest.schema_version > SCHEMA_VERSION {
+        return Err(anyhow!(
+            "Backup was written by a newer iceland (schema {}, this build supports {}). Please upgrade.",
+            manifest.schema_version,
+            SCHEMA_VERSION
+        ));
+    }
+    Ok(ExtractedBackup {
+        manifest,
+        config,
+        sessions,
+        files,
+    })
+}
+
+fn restore_backup(archive_path: &Path, area: Option<&str>, dry_run: bool) -> Result<()> {
+    if let Some(area) = area {
+        if !read_backup_manifest(archive_path)?.areas.iter().any(|a| a == area) {
+            return Err(anyhow!("Area '{}' is not in this backup.", area));
+        }
+    }
+    {
+        let _lock = lock_data_dir()?;
+        if dry_run {
+            return preview_restore(archive_path, area);
+        }
+
+        let snapshot = take_snapshot("restore", load_config()?.snapshots.max(1))?;
+        let staging = iceland_dir().join(RESTORE_STAGING_DIR);
+        if staging.exists() {
+            fs::remove_dir_all(&staging)?;
+        }
+        let extracted = match extract_backup(archive_path, area, Some(&staging)) {
+            Ok(extracted) => extracted,
+            Err(e) => {
+                let _ = fs::remove_dir_all(&staging);
+                return Err(e);
+            }
+        };
+        let restored = match area {
+            Some(area) => restore_area_from(&extracted, area, &staging),
+            None => restore_all_from(&extracted, &staging),
+        };
+        if staging.exists() {
+            fs::remove_dir_all(&staging)?;
+        }
+        restored?;
+
+        if let Some(snapshot) = snapshot {
+            println!("Previous data saved to {}", snapshot.display());
+        }
+    }
+    // A backup from an older version comes back in its old layout
+    run_migrations()
+}
+
+fn preview_restore(archive_path: &Path, area: Option<&str>) -> Result<()> {
+    let extracted = extract_backup(archive_path, area, None)?;
+    let manifest = &extracted.manifest;
+    println!(
+        "Backup from {} (iceland {}, schema {}): {} areas, {} files, checksums OK.",
+        manifest.created.format("%Y-%m-%d %H:%M"),
+        manifest.app_version,
+        manifest.schema_version,
+        manifest.areas.len(),
+        manifest.files.len()
+    );
+
+    match area {
+        Some(area) => {
+            println!(
+                "Would replace area '{}' ({} files), its settings and its sessions.",
+                area,
+                extracted.files.len()
+            );
+        }
+        None => {
+            println!("Would replace all current data with: {}", manifest.areas.join(", "));
+            let lost: Vec<_> = load_config()?
+                .areas
+                .into_keys()
+                .filter(|a| !manifest.areas.contains(a))
+                .collect();
+            if !lost.is_empty() {
+                println!("Areas not in the backup would be removed: {}", lost.join(", "));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Replace everything but iceland's bookkeeping with the staged backup.
+fn restore_all_from(extracted: &ExtractedBackup, staging: &Path) -> Result<()> {
+    for entry in fs::read_dir(iceland_dir())? {
+        let path = entry?.path();
+        let keep = path.file_name().is_some_and(|n| BACKUP_EXCLUDE.iter().any(|x| n == *x));
+        if keep || path == config_path() {
+            continue;
+        }
+        if path.is_dir() {
+            fs::remove_dir_all(&path)?;
+        } else {
+            fs::remove_file(&path)?;
+        }
+    }
+    for entry in fs::read_dir(staging)? {
+        let path = entry?.path();
+        // Older backups may still carry bookkeeping such as a running timer
+        let Some(name) = path.file_name().filter(|n| !BACKUP_EXCLUDE.iter().any(|x| n == x)) else {
+            continue;
+        };
+        fs::rename(&path, iceland_dir().join(name))?;
+    }
+    if let Some(config) = &extracted.config {
+        write_atomic(&config_path(), config)?;
+    }
+
+    println!(
+        "♻️  Restored {} areas ({} files) from the backup of {}.",
+        extracted.manifest.areas.len(),
+        extracted.manifest.files.len(),
+        extracted.manifest.created.format("%Y-%m-%d %H:%M")
+    );
+    Ok(())
+}
+
+/// Replace one area's directory, settings and sessions with the staged backup.
+fn restore_area_from(extracted: &ExtractedBackup, area: &str, staging: &Path) -> Result<()> {
+    let mut sessions = load_sessions_for_edit()?;
+    if area_dir(area).exists() {
+        move_to_trash(&format!("restore {} from backup", area), &[area_dir(area)], None)?;
+    }
+    let staged = staging.join(area);
+    if staged.exists() {
+        fs::rename(&staged, area_dir(area))?;
+    } else {
+        fs::create_dir_all(area_dir(area))?;
+    }
+
+    let mut config = load_config()?;
+    let area_config = match &extracted.config {
+        Some(content) => toml::from_str::<Config>(content)?.area(area),
+        None => AreaConfig::default(),
+    };
+    config.areas.insert(area.to_string(), area_config);
+    save_config(&config)?;
+
+    // Sessions of the area come from the backup and get fresh ids here
+    let archived = match &extracted.sessions {
+        Some(content) => parse_sessions(content)?,
+        None => Vec::new(),
+    };
+    sessions.retain(|s| s.area != area);
+    sessions.extend(archived.into_iter().filter(|s| s.area == area).map(|s| Session { id: 0, ..s }));
+    sessions.sort_by_key(|s| s.start);
+    assign_missing_ids(&mut sessions);
+    save_sessions(&sessions)?;
+
+    println!(
+        "♻️  Restored area '{}' ({} files) from the backup of {}.",
+        area,
+        extracted.files.len(),
+        extracted.manifest.created.format("%Y-%m-%d %H:%M")
+    );
+    Ok(())
+}
+
+// ==============================================
+// NOTES
+// ==============================================
+
+/// Quote a string for YAML; JSON strings are valid YAML scalars.
+fn yaml_string(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+fn yaml_value(value: &str) -> String {
+    if value.starts_with('"') {
+        serde_json::from_str(value).unwrap_or_else(|_| value.to_string())
+    } else {
+        value.trim_matches('\'').to_string()
+    }
+}
+
+fn render_note(note: &Note) -> String {
+    let mut out = String::from("---\n");
+    out += &format!("id: {}\n", yaml_string(&note.id));
+    if let Some(title) = &note.title {
+        out += &format!("title: {}\n", yaml_string(title));
+    }
+    out += &format!("area: {}\n", yaml_string(&note.area));
+    let tags: Vec<_> = note.tags.iter().map(|t| yaml_string(t)).collect();
+    out += &format!("tags: [{}]\n", tags.join(", "));
+    out += &format!("created: {}\n", note.created.to_rfc3339());
+    out += &format!("updated: {}\n", note.updated.to_rfc3339());
+    out += "---\n\n";
+    out += note.body.trim_end();
+    out += "\n";
+    out
+}
+
+/// Read a note file; `None` for Markdown files without iceland's front-matter.
+fn parse_note(area: &str, path: &Path, content: &str) -> Option<Note> {
+    let content = content.replace("\r\n", "\n");
+    let rest = content.strip_prefix("---\n")?;
+    let (header, body) = match rest.split_once("\n---\n") {
+        Some((header, body)) => (header, body),
+        None => (rest.strip_suffix("\n---")?, ""),
+    };
+
+    let fields: HashMap<&str, &str> = header
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim(), value.trim()))
+        .collect();
+    let date = |key: &str| {
+        fields
+            .get(key)
+            .and_then(|v| DateTime::parse_from_rfc3339(&yaml_value(v)).ok())
+            .map(|d| d.with_timezone(&Local))
+    };
+    let created = date("created")?;
+    let tags = fields
+        .get("tags")
+        .map(|v| {
+            v.trim_start_matches('[')
+                .trim_end_matches(']')
+                .split(',')
+                .map(|t| yaml_value(t.trim()))
+                .filter(|t| !t.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Some(Note {
+        // The file name is the id, so a hand-edited `id:` can't orphan the note
+        id: path.file_stem()?.to_string_lossy().into_owned(),
+        title: fields.get("title").map(|v| yaml_value(v)).filter(|t| !t.is_empty()),
+        area: area.to_string(),
+        tags,
+        created,
+        updated: date("updated").unwrap_or(created),
+        body: body.trim_start_matches('\n').trim_end().to_string(),
+        path: path.to_path_buf(),
+    })
+}
+
+fn write_note(note: &Note) -> Result<()> {
+    if let Some(parent) = note.path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    write_atomic(&note.path, render_note(note))
+}
+
+/// Notes of one area, oldest first.
+fn load_area_notes(area: &str) -> Result<Vec<Note>> {
+    let notes_dir = area_dir(area).join("notes");
+    let mut notes = Vec::new();
+    if !notes_dir.is_dir() {
+        return Ok(notes);
+    }
+    for entry in fs::read_dir(&notes_dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|e| e == "md") {
+            if let Some(note) = parse_note(area, &path, &fs::read_to_string(&path)?) {
+                notes.push(note);
+            }
+        }
+    }
+    notes.sort_by(|a, b| a.created.cmp(&b.created).then_with(|| a.id.cmp(&b.id)));
+    Ok(notes)
+}
+
+fn load_notes(area: Option<&str>) -> Result<Vec<Note>> {
+    let config = load_config()?;
+    let mut notes = Vec::new();
+    for name in config.areas.keys().filter(|name| area.is_none_or(|a| a == *name)) {
+        notes.extend(load_area_notes(name)?);
+    }
+    notes.sort_by(|a, b| a.created.cmp(&b.created).then_with(|| a.id.cmp(&b.id)));
+    Ok(notes)
+}
+
+/// Look a note up by `<id>` or `<area>/<id>`.
+fn find_note(reference: &str) -> Result<Note> {
+    let (area, id) = match reference.split_once('/') {
+        Some((area, id)) => (Some(area), id),
+        None => (None, reference),
+    };
+    let mut matches: Vec<Note> = load_notes(area)?.into_iter().filter(|n| n.id == id).collect();
+    match matches.len() {
+        0 => Err(anyhow!("No note '{}'. See `iceland notes list`.", reference)),
+        1 => Ok(matches.remove(0)),
+        _ => {
+            let areas: Vec<_> = matches.iter().map(|n| n.area.as_str()).collect();
+            Err(anyhow!(
+                "Note '{}' exists in several areas ({}); use <area>/{}.",
+                id,
+                areas.join(", "),
+                id
+            ))
+        }
+    }
+}
+
+/// A new id from the current time, unique across all areas.
+fn new_note_id() -> Result<String> {
+    let taken: Vec<String> = load_notes(None)?.into_iter().map(|n| n.id).collect();
+    let base = Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let mut id = base.clone();
+    let mut n = 1;
+    while taken.contains(&id) {
+        n += 1;
+        id = format!("{}-{}", base, n);
+    }
+    Ok(id)
+}
+
+/// $VISUAL, then $EDITOR, then vi.
+fn open_in_editor(path: &Path) -> Result<()> {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return Err(anyhow!("No terminal for an editor. Pass the text, or `-` to read it from stdin."));
+    }
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+    let words = shell_words::split(&editor).with_context(|| format!("Could not parse editor '{}'", editor))?;
+    let (program, args) = words.split_first().ok_or_else(|| anyhow!("Editor command is empty"))?;
+
+    let status = Command::new(program)
+        .args(args)
+        .arg(path)
+        .status()
+        .with_context(|| format!("Could not start editor '{}'", program))?;
+    if !status.success() {
+        return Err(anyhow!("Editor '{}' exited with {}", program, status));
+    }
+    Ok(())
+}
+
+/// Create an empty, private temp file for the editor. `create_new` refuses
+/// existing paths (and symlinks planted there); the suffix is hard to guess.
+fn create_temp_note() -> Result<PathBuf> {
+    use std::hash::{BuildHasher, Hasher};
+    for _ in 0..100 {
+        // RandomState is seeded from the OS, which is all the randomness needed here
+        let random = std::collections::hash_map::RandomState::new().build_hasher().finish();
+        let suffix = format!("{}-{:016x}", std::process::id(), random);
+        let path = std::env::temp_dir().join(format!("iceland-note-{}.md", suffix));
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        match options.open(&path) {
+            Ok(_) => return Ok(path),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e).context("Could not create a temp file for the editor"),
+        }
+    }
+    Err(anyhow!("Could not create a temp file for the editor"))
+}
+
+/// The note text from the argument, stdin (`-`) or an editor on a temp file.
+fn read_note_text(text: Option<String>) -> Result<String> {
+    match text.as_deref() {
+        Some("-") => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input)?;
+            Ok(input)
+        }
+        Some(_) => Ok(text.unwrap_or_default()),
+        None => {
+            let path = create_temp_note()?;
+            let edited = open_in_editor(&path).and_then(|_| Ok(fs::read_to_string(&path)?));
+            let _ = fs::remove_file(&path);
+            edited
+        }
+    }
+}
+
+fn add_note(area: &str, text: Option<String>, title: Option<String>, tags: &[String]) -> Result<()> {
+    let notes_dir = area_dir(area).join("notes");
+    if !notes_dir.exists() {
+        return Err(anyhow!("Area '{}' does not exist or has no notes folder.", area));
+    }
+
+    // Gather the text before locking; the editor may stay open a while
+    let text = read_note_text(text)?;
+    let text = text.trim_end();
+    if text.trim().is_empty() {
+        println!("Empty note, nothing saved.");
+        return Ok(());
+    }
+
+    let lock = lock_data_dir()?;
+    let id = new_note_id()?;
+    let now = Local::now();
+    let note = Note {
+        path: notes_dir.join(format!("{}.md", id)),
+        id,
+        title,
+        area: area.to_string(),
+        tags: tags.iter().map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect(),
+        created: now,
+        updated: now,
+        body: text.to_string(),
+    };
+    write_note(&note)?;
+    index_file_now(area, &note.path, SearchScope::Notes)?;
+    println!("📝 Note {} added to {}", note.id, note.path.display());
+    drop(lock);
+    run_hooks(
+        HookEvent::NoteAdded,
+        area,
+        &[
+            ("ICELAND_NOTE_ID", note.id.clone()),
+            ("ICELAND_NOTE_FILE", note.path.to_string_lossy().into_owned()),
+        ],
+    )
+}
+
+/// Title, or the first line of the body, for one-line listings.
+fn note_summary(note: &Note) -> String {
+    let summary = note
+        .title
+        .clone()
+        .or_else(|| note.body.lines().find(|l| !l.trim().is_empty()).map(str::to_string))
+        .unwrap_or_default();
+    if summary.chars().count() > 60 {
+        format!("{}…", summary.chars().take(59).collect::<String>())
+    } else {
+        summary
+    }
+}
+
+fn manage_notes(action: NotesAction) -> Result<()> {
+    match action {
+        NotesAction::Add {
+            area,
+            text,
+            title,
+            tags,
+        } => add_note(&area, text, title, &tags),
+        NotesAction::List { area, tag, since } => {
+            let notes: Vec<Note> = load_notes(area.as_deref())?
+                .into_iter()
+                .filter(|n| tag.as_ref().is_none_or(|t| n.tags.contains(t)))
+                .filter(|n| since.is_none_or(|day| n.created.date_naive() >= day))
+                .collect();
+            if notes.is_empty() {
+                println!("No notes found.");
+                return Ok(());
+            }
+            for note in notes {
+                let tags: String = note.tags.iter().map(|t| format!(" #{}", t)).collect();
+                println!(
+                    "{}  {}  {:<10} {}{}",
+                    note.id,
+                    note.created.format("%Y-%m-%d %H:%M"),
+                    note.area,
+                    note_summary(&note),
+                    tags
+                );
+            }
+            Ok(())
+        }
+        NotesAction::Show { id } => {
+            let note = find_note(&id)?;
+            if let Some(title) = &note.title {
+                println!("# {}", title);
+            }
+            println!(
+                "{} · {} · created {}{}",
+                note.id,
+                note.area,
+                note.created.format("%Y-%m-%d %H:%M"),
+                if note.updated != note.created {
+                    format!(", updated {}", note.updated.format("%Y-%m-%d %H:%M"))
+                } else {
+                    String::new()
+                }
+            );
+            if !note.tags.is_empty() {
+                let tags: Vec<_> = note.tags.iter().map(|t| format!("#{}", t)).collect();
+                println!("{}", tags.join(" "));
+            }
+            println!("\n{}", note.body.trim_end());
+            Ok(())
+        }
+        NotesAction::Edit { id } => edit_note(&id),
+    }
+}
+
+/// Open a note in the editor, then bump `updated` if it changed.
+fn edit_note(reference: &str) -> Result<()> {
+    let note = find_note(reference)?;
+    let before = fs::read_to_string(&note.path)?;
+    open_in_editor(&note.path)?;
+
+    let _lock = lock_data_dir()?;
+    let after = fs::read_to_string(&note.path)?;
+    if after == before {
+        println!("No changes to note {}.", note.id);
+        return Ok(());
+    }
+    let mut edited = parse_note(&note.area, &note.path, &after).ok_or_else(|| {
+        anyhow!(
+            "The front-matter of {} is no longer readable (it needs `created:`); fix it by hand.",
+            note.path.display()
+        )
+    })?;
+    edited.updated = Local::now();
+    write_note(&edited)?;
+    index_file_now(&edited.area, &edited.path, SearchScope::Notes)?;
+    println!("📝 Note {} updated.", edited.id);
+    Ok(())
+}
+
+// ==============================================
+// JOURNAL
+// ==============================================
+
+fn journal_dir(area: &str) -> PathBuf {
+    area_dir(area).join("notes").join(JOURNAL_DIR)
+}
+
+/// Time and sessions tracked in `area` on `day`, as a Markdown block between the summary markers.
+/// A session counts for the day it started on, as in `stats`.
+fn journal_summary(area: &str, day: NaiveDate) -> Result<String> {
+    let now = Local::now();
+    // (session, still running); a running session is counted up to now
+    let mut spans: Vec<(Session, bool)> = load_sessions()?
+        .into_iter()
+        .filter(|s| s.area == area && s.start.date_naive() == day)
+        .map(|s| (s, false))
+        .collect();
+    if let Some(running) = read_running_session()? {
+        if running.area == area && running.start.date_naive() == day {
+            let session = Session {
+                area: running.area,
+                start: running.start,
+                end: now,
+                tag: running.tag,
+                note: running.note,
+                planned: running.planned,
+                id: 0,
+            };
+            spans.push((session, true));
+        }
+    }
+    spans.sort_by_key(|(s, _)| s.start);
+
+    let total = spans.iter().fold(Duration::zero(), |sum, (s, _)| sum + (s.end - s.start));
+    let mut out = format!("{}\n", JOURNAL_SUMMARY_START);
+    match spans.len() {
+        0 => out += "_No sessions tracked._\n",
+        n => {
+            out += &format!(
+                "**{} h** tracked in {} session{}\n\n",
+                format_duration(total),
+                n,
+                if n == 1 { "" } else { "s" }
+            );
+            for (session, running) in &spans {
+                let until = if *running { "now".to_string() } else { session.end.format("%H:%M").to_string() };
+                out += &format!(
+                    "- {}–{} ({} h)",
+                    session.start.format("%H:%M"),
+                    until,
+                    format_duration(session.end - session.start)
+                );
+                if let Some(tag) = &session.tag {
+                    out += &format!(" #{}", tag);
+                }
+                if let Some(note) = &session.note {
+                    out += &format!(" — {}", note);
+                }
+                out += "\n";
+            }
+        }
+    }
+    out += JOURNAL_SUMMARY_END;
+    Ok(out)
+}
+
+/// Replace the summary between the markers of an entry, or put it on top if they are gone.
+fn splice_journal_summary(existing: &str, summary: &str) -> String {
+    match (existing.find(JOURNAL_SUMMARY_START), existing.find(JOURNAL_SUMMARY_END)) {
+        (Some(start), Some(end)) if start < end => format!(
+            "{}{}{}",
+            &existing[..start],
+            summary,
+            &existing[end + JOURNAL_SUMMARY_END.len()..]
+        ),
+        _ => format!("{}\n\n{}", summary, existing),
+    }
+}
+
+/// Create the day's entry, or refresh the session summary of an existing one.
+/// Caller must hold the data lock.
+fn prepare_journal_entry(area: &str, day: NaiveDate) -> Result<PathBuf> {
+    let path = journal_dir(area).join(format!("{}.md", day.format("%Y-%m-%d")));
+    let summary = journal_summary(area, day)?;
+    let content = match fs::read_to_string(&path) {
+        Ok(existing) => splice_journal_summary(&existing, &summary),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            format!("# {} — {}\n\n{}\n\n", area, day.format("%A, %B %-d, %Y"), summary)
+        }
+        Err(e) => return Err(e.into()),
+    };
+    fs::create_dir_all(journal_dir(area))?;
+    write_atomic(&path, content)?;
+    Ok(path)
+}
+
+fn journal(area: Option<String>, text: Option<String>, day: NaiveDate) -> Result<()> {
+    let area = match area.or(read_current_area()?) {
+        Some(area) => area,
+        None => return Err(anyhow!("No current area. Pass one: iceland journal <area>")),
+    };
+    if !load_config()?.areas.contains_key(&area) {
+        return Err(anyhow!("Area '{}' does not exist.", area));
+    }
+
+    let path = {
+        let _lock = lock_data_dir()?;
+        prepare_journal_entry(&area, day)?
+    };
+    match text {
+        // Editing happens unlocked; the editor may stay open a while
+        None => open_in_editor(&path)?,
+        Some(text) => {
+            let text = read_note_text(Some(text))?;
+            let text = text.trim_end();
+            if text.trim().is_empty() {
+                println!("Empty entry, nothing appended.");
+                return Ok(());
+            }
+            let _lock = lock_data_dir()?;
+            let mut content = fs::read_to_string(&path)?;
+            if !content.ends_with("\n\n") {
+                content += if content.ends_with('\n') { "\n" } else { "\n\n" };
+            }
+            content += &format!("### {}\n\n{}\n", Local::now().format("%H:%M"), text);
+            write_atomic(&path, content)?;
+            println!("📓 Added to {}", path.display());
+        }
+    }
+
+    let _lock = lock_data_dir()?;
+    index_file_now(&area, &path, SearchScope::Notes)
+}
+
+fn list_journal(area: Option<&str>) -> Result<()> {
+    let config = load_config()?;
+    if let Some(area) = area {
+        if !config.areas.contains_key(area) {
+            return Err(anyhow!("Area '{}' does not exist.", area));
+        }
+    }
+
+    let mut days: IndexMap<NaiveDate, Vec<&str>> = IndexMap::new();
+    for name in config.areas.keys().filter(|name| area.is_none_or(|a| a == *name)) {
+        let dir = journal_dir(name);
+        if !dir.is_dir() {
+            continue;
+        }
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            let day = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.strip_suffix(".md"))
+                .and_then(|n| NaiveDate::parse_from_str(n, "%Y-%m-%d").ok());
+            if let Some(day) = day {
+                days.entry(day).or_default().push(name);
+            }
+        }
+    }
+    if days.is_empty() {
+        println!("No journal entries yet. Start one with `iceland journal`.");
+        return Ok(());
+    }
+    days.sort_by(|a, _, b, _| b.cmp(a));
+    for (day, areas) in &days {
+        println!("{}  {}", day.format("%Y-%m-%d %a"), areas.join(", "));
+    }
+    println!("\n{} day{} with entries", days.len(), if days.len() == 1 { "" } else { "s" });
+    Ok(())
+}
+
+// ==============================================
+// INBOX
+// ==============================================
+
+fn capture(text: Option<String>, area: Option<String>, tags: &[String]) -> Result<()> {
+    let area = match area.or(read_current_area()?) {
+        Some(area) => area,
+        None => INBOX_AREA.to_string(),
+    };
+    if area == INBOX_AREA && !load_config()?.areas.contains_key(INBOX_AREA) {
+        add_area(INBOX_AREA, None)?;
+    }
+    add_note(&area, text, None, tags)
+}
+
+fn list_inbox() -> Result<()> {
+    let items = load_area_notes(INBOX_AREA)?;
+    if items.is_empty() {
+        println!("📥 Inbox is empty.");
+        return Ok(());
+    }
+    for note in &items {
+        println!("{}  {}  {}", note.id, note.created.format("%Y-%m-%d %H:%M"), note_summary(note));
+    }
+    println!("\n📥 {} item(s). Sort them with `iceland inbox triage`.", items.len());
+    Ok(())
+}
+
+/// The note as a single line, for links and flashcards.
+fn note_as_line(note: &Note) -> String {
+    let body: Vec<&str> = note.body.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+    match &note.title {
+        Some(title) if body.is_empty() => title.clone(),
+        Some(title) => format!("{}: {}", title, body.join(" ")),
+        None => body.join(" "),
+    }
+}
+
+/// Append one line to a text file, adding a missing final newline first.
+fn append_line(path: &Path, line: &str) -> Result<()> {
+    let mut content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content += line;
+    content.push('\n');
+    write_atomic(path, content)
+}
+
+/// Move an inbox note into `area`'s notes. Caller must hold the data lock.
+fn file_note_in(note: &Note, area: &str) -> Result<PathBuf> {
+    let notes_dir = area_dir(area).join("notes");
+    let mut path = notes_dir.join(format!("{}.md", note.id));
+    let mut n = 1;
+    while path.exists() {
+        n += 1;
+        path = notes_dir.join(format!("{}-{}.md", note.id, n));
+    }
+    write_note(&Note {
+        id: path.file_stem().unwrap_or_default().to_string_lossy().into_owned(),
+        area: area.to_string(),
+        path: path.clone(),
+        ..note.clone()
+    })?;
+    fs::remove_file(&note.path)?;
+    index_file_now(area, &path, SearchScope::Notes)?;
+    Ok(path)
+}
+
+/// Ask for a deck and the two sides; returns the deck and the card line to append.
+fn ask_flashcard(note: &Note, area: &str, theme: &ColorfulTheme) -> Result<(PathBuf, String)> {
+    let decks = deck_files(area)?;
+    let mut options: Vec<String> = decks
+        .iter()
+        .filter_map(|d| d.file_name().map(|n| n.to_string_lossy().into_owned()))
+        .collect();
+    options.push("New deck…".to_string());
+    let choice = Select::with_theme(theme)
+        .with_prompt("Which deck?")
+        .items(&options)
+        .default(0)
+        .interact()?;
+    let deck = match decks.get(choice) {
+        Some(deck) => deck.clone(),
+        None => {
+            let name: String = Input::with_theme(theme)
+                .with_prompt("Deck name")
+                .default("inbox.txt".to_string())
+                .interact_text()?;
+            let name = name.trim();
+            if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+                return Err(anyhow!("'{}' cannot be used as a deck name.", name));
+            }
+            let name = if Path::new(name).extension().is_some() { name.to_string() } else { format!("{}.txt", name) };
+            area_dir(area).join("flashcards").join(name)
+        }
+    };
+
+    // "front|back" notes are split for you; otherwise the title (or first line) is the front
+    let line = note_as_line(note);
+    let (front, back) = match line.split_once('|') {
+        Some((front, back)) => (front.trim().to_string(), back.trim().to_string()),
+        None => match &note.title {
+            Some(title) => (title.clone(), note.body.lines().map(str::trim).collect::<Vec<_>>().join(" ")),
+            None => (line, String::new()),
+        },
+    };
+    let front: String = Input::with_theme(theme)
+        .with_prompt("Front")
+        .with_initial_text(front)
+        .interact_text()?;
+    let back: String = Input::with_theme(theme)
+        .with_prompt("Back")
+        .with_initial_text(back.trim())
+        .interact_text()?;
+
+    Ok((deck, format!("{}|{}", front.trim().replace('|', "/"), back.trim())))
+}
+
+/// Where an inbox item goes, decided before the data lock is taken.
+enum Triage {
+    Trash,
+    Note(String),
+    Link(String),
+    Flashcard(PathBuf, String),
+}
+
+fn triage_inbox() -> Result<()> {
+    if !io::stdin().is_terminal() {
+        return Err(anyhow!("`inbox triage` asks where each item goes; run it in a terminal."));
+    }
+    // The lock is only taken per item, once its destination is known, so other
+    // commands aren't blocked while the prompts are open
+    let items = load_area_notes(INBOX_AREA)?;
+    if items.is_empty() {
+        println!("📥 Inbox is empty.");
+        return Ok(());
+    }
+    let config = load_config()?;
+    let areas: Vec<&String> = config
+        .areas
+        .iter()
+        .filter(|(name, area)| *name != INBOX_AREA && !area.archived)
+        .map(|(name, _)| name)
+        .collect();
+    if areas.is_empty() {
+        return Err(anyhow!("No areas to sort into yet. Create one with `iceland add-area <name>`."));
+    }
+
+    let theme = ColorfulTheme::default();
+    let (mut moved, mut trashed) = (0, 0);
+    for (i, note) in items.iter().enumerate() {
+        let tags: String = note.tags.iter().map(|t| format!(" #{}", t)).collect();
+        println!("\n[{}/{}] {}{}", i + 1, items.len(), note.created.format("%Y-%m-%d %H:%M"), tags);
+        if let Some(title) = &note.title {
+            println!("# {}", title);
+        }
+        println!("{}", note.body.trim_end());
+
+        let action = Select::with_theme(&theme)
+            .with_prompt("File it as")
+            .items(&["A note", "A link", "A flashcard", "Skip", "Move to trash", "Stop"])
+            .default(0)
+            .interact()?;
+        let choose_area = || -> Result<String> {
+            let index = Select::with_theme(&theme)
+                .with_prompt("Which area?")
+                .items(&areas)
+                .default(0)
+                .interact()?;
+            Ok(areas[index].clone())
+        };
+        let triage = match action {
+            0 => Triage::Note(choose_area()?),
+            1 => Triage::Link(choose_area()?),
+            2 => {
+                let (deck, card) = ask_flashcard(note, &choose_area()?, &theme)?;
+                Triage::Flashcard(deck, card)
+            }
+            3 => continue,
+            4 => Triage::Trash,
+            _ => break,
+        };
+
+        let _lock = lock_data_dir()?;
+        if !note.path.exists() {
+            println!("Already gone from the inbox, skipped.");
+            continue;
+        }
+        let dest = match triage {
+            Triage::Trash => {
+                move_to_trash(&format!("inbox {}", note.id), std::slice::from_ref(&note.path), None)?;
+                trashed += 1;
+                continue;
+            }
+            Triage::Note(area) => file_note_in(note, &area)?,
+            Triage::Link(area) => {
+                let links = area_dir(&area).join("links.txt");
+                append_line(&links, &note_as_line(note))?;
+                fs::remove_file(&note.path)?;
+                links
+            }
+            Triage::Flashcard(deck, card) => {
+                fs::create_dir_all(deck.parent().unwrap_or(Path::new(".")))?;
+                append_line(&deck, &card)?;
+                fs::remove_file(&note.path)?;
+                deck
+            }
+        };
+        println!("→ {}", dest.display());
+        moved += 1;
+    }
+
+    let left = load_area_notes(INBOX_AREA)?.len();
+    println!("\n📥 {} moved, {} trashed, {} left in the inbox.", moved, trashed, left);
+    Ok(())
+}
+
+// ==============================================
+// SEARCH
+// ==============================================
+
+/// Files of an area that `search` covers; hidden files are skipped.
+fn searchable_files(area: &str) -> Result<Vec<(PathBuf, SearchScope)>> {
+    let area_path = area_dir(area);
+    let mut files = Vec::new();
+    let notes_dir = area_path.join("notes");
+    if notes_dir.is_dir() {
+        let walker = WalkDir::new(&notes_dir)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'));
+        for entry in walker {
+            let entry = entry?;
+            if entry.file_type().is_file() {
+                files.push((entry.into_path(), SearchScope::Notes));
+            }
+        }
+    }
+    let links = area_path.join("links.txt");
+    if links.is_file() {
+        files.push((links, SearchScope::Links));
+    }
+    files.extend(deck_files(area)?.into_iter().map(|deck| (deck, SearchScope::Flashcards)));
+    Ok(files)
+}
+
+/// Size and mtime, to tell whether an index entry is stale.
+fn file_stamp(path: &Path) -> Result<(u64, u64)> {
+    let meta = fs::metadata(path)?;
+    let modified = meta
+        .modified()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0);
+    Ok((modified, meta.len()))
+}
+
+/// The lines worth searching: note front-matter is reduced to its title and tags.
+fn index_lines(path: &Path, scope: SearchScope) -> Result<Vec<IndexedLine>> {
+    let bytes = fs::read(path)?;
+    if bytes.contains(&0) {
+        return Ok(Vec::new()); // binary attachment
+    }
+    let content = String::from_utf8_lossy(&bytes);
+    let mut lines = Vec::new();
+    let mut in_front_matter = scope == SearchScope::Notes && content.starts_with("---");
+    for (i, text) in content.lines().enumerate() {
+        let line = i + 1;
+        if in_front_matter {
+            if i > 0 && text.trim_end() == "---" {
+                in_front_matter = false;
+            } else if let Some((key, value)) = text.split_once(':') {
+                match key.trim() {
+                    "title" => lines.push(IndexedLine {
+                        line,
+                        text: yaml_value(value.trim()),
+                        heading: true,
+                    }),
+                    "tags" => {
+                        let tags: Vec<_> = value
+                            .trim()
+                            .trim_start_matches('[')
+                            .trim_end_matches(']')
+                            .split(',')
+                            .map(|t| yaml_value(t.trim()))
+                            .filter(|t| !t.is_empty())
+                            .map(|t| format!("#{}", t))
+                            .collect();
+                        if !tags.is_empty() {
+                            lines.push(IndexedLine {
+                                line,
+                                text: tags.join(" "),
+                                heading: false,
+                            });
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            continue;
+        }
+        if !text.trim().is_empty() {
+            lines.push(IndexedLine {
+                line,
+                text: text.to_string(),
+                heading: scope == SearchScope::Notes && text.starts_with('#'),
+            });
+        }
+    }
+    Ok(lines)
+}
+
+fn index_key(path: &Path) -> String {
+    let relative = path.strip_prefix(iceland_dir()).unwrap_or(path);
+    relative.to_string_lossy().replace('\\', "/")
+}
+
+fn load_index() -> SearchIndex {
+    // A missing, corrupt or outdated index is simply rebuilt
+    fs::read_to_string(index_file())
+        .ok()
+        .and_then(|content| serde_json::from_str::<SearchIndex>(&content).ok())
+        .filter(|index| index.version == INDEX_VERSION)
+        .unwrap_or(SearchIndex {
+            version: INDEX_VERSION,
+            files: IndexMap::new(),
+        })
+}
+
+/// Caller must hold the data lock.
+fn save_index(index: &SearchIndex) -> Result<()> {
+    let path = index_file();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    write_atomic(&path, serde_json::to_string(index)?)
+}
+
+/// Re-read `path` into the index if it changed. Returns whether it did.
+fn update_index_entry(index: &mut SearchIndex, area: &str, path: &Path, scope: SearchScope) -> Result<bool> {
+    let key = index_key(path);
+    let (modified, size) = file_stamp(path)?;
+    if let Some(entry) = index.files.get(&key) {
+        if entry.area == area && entry.modified == modified && entry.size == size {
+            return Ok(false);
+        }
+    }
+    let entry = IndexedFile {
+        area: area.to_string(),
+        scope,
+        modified,
+        size,
+        lines: index_lines(path, scope)?,
+    };
+    index.files.insert(key, entry);
+    Ok(true)
+}
+
+/// Index one file that was just written. Caller must hold the data lock.
+fn index_file_now(area: &str, path: &Path, scope: SearchScope) -> Result<()> {
+    let mut index = load_index();
+    if update_index_entry(&mut index, area, path, scope)? {
+        save_index(&index)?;
+    }
+    Ok(())
+}
+
+/// Bring the index up to date with every area's files. Caller must hold the data lock.
+fn refresh_index(config: &Config) -> Result<SearchIndex> {
+    let mut index = load_index();
+    let mut changed = false;
+    let mut seen = HashSet::new();
+    for area in config.areas.keys() {
+        for (path, scope) in searchable_files(area)? {
+            changed |= update_index_entry(&mut index, area, &path, scope)?;
+            seen.insert(index_key(&path));
+        }
+    }
+    let before = index.files.len();
+    index.files.retain(|key, _| seen.contains(key));
+    changed |= index.files.len() != before;
+    if changed {
+        index.files.sort_keys();
+        save_index(&index)?;
+    }
+    Ok(index)
+}
+
+struct SearchHit<'a> {
+    score: usize,
+    modified: u64,
+    path: &'a str,
+    area: &'a str,
+    line: &'a IndexedLine,
+}
+
+/// Matches count once each; whole words, exact case and headings rank higher.
+fn score_line(pattern: &Regex, query: &str, line: &IndexedLine) -> usize {
+    let text = &line.text;
+    let mut score = 0;
+    for m in pattern.find_iter(text) {
+        score += 2;
+        let before = text[..m.start()].chars().next_back();
+        let after = text[m.end()..].chars().next();
+        if !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric) {
+            score += 3;
+        }
+        if m.as_str() == query {
+            score += 1;
+        }
+    }
+    if score > 0 && line.heading {
+        score += 5;
+    }
+    score
+}
+
+/// The line trimmed to about `width` characters around the first match, matches highlighted.
+fn snippet(pattern: &Regex, text: &str, width: usize) -> String {
+    let text = text.trim();
+    let first = pattern.find(text).map(|m| m.start()).unwrap_or(0);
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let at = chars.iter().position(|(i, _)| *i >= first).unwrap_or(0);
+    let start = at.saturating_sub(width / 3);
+    let end = (start + width).min(chars.len());
+    let byte = |i: usize| chars.get(i).map(|(b, _)| *b).unwrap_or(text.len());
+    let window = &text[byte(start)..byte(end)];
+
+    let highlight = Style::new().bold().yellow();
+    let mut out = String::new();
+    if start > 0 {
+        out.push('…');
+    }
+    let mut last = 0;
+    for m in pattern.find_iter(window) {
+        out += &window[last..m.start()];
+        out += &highlight.apply_to(m.as_str()).to_string();
+        last = m.end();
+    }
+    out += &window[last..];
+    if end < chars.len() {
+        out.push('…');
+    }
+    out
+}
+
+fn search(query: &str, area: Option<&str>, scopes: &[SearchScope], regex: bool, limit: usize) -> Result<()> {
+    let config = load_config()?;
+    if let Some(area) = area {
+        if !config.areas.contains_key(area) {
+            return Err(anyhow!("Area '{}' does not exist.", area));
+        }
+    }
+    let source = if regex { query.to_string() } else { regex::escape(query) };
+    let pattern = RegexBuilder::new(&source)
+        .case_insensitive(true)
+        .build()
+        .with_context(|| format!("Invalid regular expression '{}'", query))?;
+
+    let index = {
+        let _lock = lock_data_dir()?;
+        refresh_index(&config)?
+    };
+
+    let mut hits = Vec::new();
+    for (path, file) in &index.files {
+        if area.is_some_and(|a| a != file.area) || !(scopes.is_empty() || scopes.contains(&file.scope)) {
+            continue;
+        }
+        for line in &file.lines {
+            let score = score_line(&pattern, query, line);
+            if score > 0 {
+                hits.push(SearchHit {
+                    score,
+                    modified: file.modified,
+                    path,
+                    area: &file.area,
+                    line,
+                });
+            }
+        }
+    }
+    if hits.is_empty() {
+        println!("No matches for '{}'.", query);
+        return Ok(());
+    }
+    hits.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then(b.modified.cmp(&a.modified))
+            .then(a.path.cmp(b.path))
+            .then(a.line.line.cmp(&b.line.line))
+    });
+
+    let dim = Style::new().dim();
+    for hit in hits.iter().take(limit) {
+        // Paths are shown relative to the area: notes/….md, links.txt, flashcards/…
+        let file = hit.path.strip_prefix(&format!("{}/", hit.area)).unwrap_or(hit.path);
+        println!(
+            "{:<10} {}  {}",
+            hit.area,
+            dim.apply_to(format!("{}:{}", file, hit.line.line)),
+            snippet(&pattern, &hit.line.text, 80)
+        );
+    }
+    if hits.len() > limit {
+        println!("… {} more (use --limit to see them)", hits.len() - limit);
+    }
+    Ok(())
+}
+
+// ==============================================
+// FLASHCARDS
+// ==============================================
+
+fn deck_state_path(deck_path: &Path) -> PathBuf {
+    let name = deck_path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    deck_path.with_file_name(format!(".{}{}", name, FLASHCARD_STATE_SUFFIX))
+}
+
+fn read_deck(deck_path: &Path) -> Result<Vec<(String, String)>> {
+    let reader = BufReader::new(File::open(deck_path)?);
+    let mut cards = vec![];
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if let Some((front, back)) = line.split_once('|') {
+            cards.push((front.to_string(), back.to_string()));
+        } else {
+            eprintln!("Warning: line {} skipped (no '|' separator)", i + 1);
+        }
+    }
+    Ok(cards)
+}
+
+fn load_card_states(deck_path: &Path) -> Result<HashMap<String, CardState>> {
+    let path = deck_state_path(deck_path);
+    let mut states = HashMap::new();
+    if !path.exists() {
+        return Ok(states);
+    }
+    let mut rdr = csv::Reader::from_path(path)?;
+    for result in rdr.deserialize() {
+        let state: CardState = result?;
+        states.insert(state.front.clone(), state);
+    }
+    Ok(states)
+}
+
+/// Write the deck's review state. Caller must hold the data lock.
+fn save_card_states(
+    deck_path: &Path,
+    cards: &[(String, String)],
+    states: &HashMap<String, CardState>,
+) -> Result<()> {
+    let mut wtr = csv::Writer::from_writer(vec![]);
+    // Keep file order and drop state for cards removed from the deck
+    for (front, _) in cards {
+        if let Some(state) = states.get(front) {
+            wtr.serialize(state)?;
+        }
+    }
+    let data = wtr.into_inner().map_err(|e| anyhow!("{}", e))?;
+    write_atomic(&deck_state_path(deck_path), data)?;
+    Ok(())
+}
+
+fn is_card_due(states: &HashMap<String, CardState>, front: &str, today: NaiveDate) -> bool {
+    states.get(front).is_none_or(|s| s.due <= today)
+}
+
+fn tui_flashcards(area: &str) -> Result<()> {
+    let flashcards_dir = area_dir(area).join("flashcards");
+    if !flashcards_dir.exists() {
+        return Err(anyhow!("No flashcards found for area '{}'.", area));
+    }
+
+    let today = Local::now().date_naive();
+    let mut decks = vec![];
+    for entry in fs::read_dir(&flashcards_dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.is_file() {
+            if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                // Hidden files hold review state, not cards
+                if !name.starts_with('.') {
+                    decks.push(name.to_string());
+                }
+            }
+        }
+    }
+    decks.sort();
+
+    if decks.is_empty() {
+        println!("No flashcard decks available.");
+        return Ok(());
+    }
+
+    let mut items = vec![];
+    for deck in &decks {
+        let deck_path = flashcards_dir.join(deck);
+        let states = load_card_states(&deck_path)?;
+        let due = read_deck(&deck_path)?
+            .iter()
+            .filter(|(front, _)| is_card_due(&states, front, today))
+            .count();
+        items.push(format!("{} ({} due)", deck, due));
+    }
+
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Select a deck")
+        .items(&items)
+        .default(0)
+        .interact()?;
+
+    let deck_path = flashcards_dir.join(&decks[selection]);
+    let cards = read_deck(&deck_path)?;
+
+    if cards.is_empty() {
+        println!("Deck is empty or malformed.");
+        return Ok(());
+    }
+
+    let mut states = load_card_states(&deck_path)?;
+    let due: Vec<_> = cards
+        .iter()
+        .filter(|(front, _)| is_card_due(&states, front, today))
+        .collect();
+
+    if due.is_empty() {
+        let next = states.values().map(|s| s.due).min();
+        match next {
+            Some(date) => println!("No cards due. Next review: {}", date),
+            None => println!("No cards due."),
+        }
+        return Ok(());
+    }
+
+    let grades: Vec<_> = Grade::ALL.iter().map(|g| g.label()).collect();
+    println!("\n--- Starting flashcards ---");
+    for (i, (front, back)) in due.iter().enumerate() {
+        println!("\nCard {} of {}", i + 1, due.len());
+        println!("Front: {}", front);
+        println!("Press Enter to reveal back...");
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        println!("Back: {}", back);
+
+        let choice = Select::with_theme(&ColorfulTheme::default())
+            .with_prompt("How well did you know it?")
+            .items(&grades)
+            .default(2)
+            .interact()?;
+
+        let state = states
+            .entry(front.clone())
+            .or_insert_with(|| CardState::new(front, today));
+        state.review(Grade::ALL[choice], today);
+        println!("Next review: {}", state.due);
+
+        // Persist after every card so an interrupted run keeps its progress
+        let _lock = lock_data_dir()?;
+        save_card_states(&deck_path, &cards, &states)?;
+    }
+    println!("--- Finished deck ---");
+    Ok(())
+}
+
+// ==============================================
+// AREA MANAGEMENT
+// ==============================================
+
+fn list_areas(show_archived: bool) -> Result<()> {
+    let config = load_config()?;
+    let current = read_current_area()?.unwrap_or_default();
+
+    println!("Available areas:");
+    for (area, area_config) in &config.areas {
+        if area_config.archived && !show_archived {
+            continue;
+        }
+        let marker = if *area == current { "▶" } else { " " };
+        let archived = if area_config.archived { " (archived)" } else { "" };
+        match &area_config.description {
+            Some(description) => println!(
+                "  {} {}{} – {}",
+                marker,
+                styled_area_name(area, area_config),
+                archived,
+                description
+            ),
+            None => println!("  {} {}{}", marker, styled_area_name(area, area_config), archived),
+        }
+    }
+    Ok(())
+}
+
+fn validate_area_name(name: &str) -> Result<()> {
+    let reserved = [TEMPLATES_DIR];
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) || reserved.contains(&name) {
+        return Err(anyhow!("'{}' cannot be used as an area name.", name));
+    }
+    Ok(())
+}
+
+fn add_area(name: &str, template: Option<String>) -> Result<()> {
+    validate_area_name(name)?;
+    let _lock = lock_data_dir()?;
+    let mut config = load_config()?;
+    if config.areas.contains_key(name) {
+        return Err(anyhow!("Area '{}' already exists.", name));
+    }
+
+    let template_name = template.unwrap_or_else(|| default_template_for(name));
+    let template = load_template(&template_name)?;
+    create_area_structure(name, &template)?;
+    config.areas.insert(name.to_string(), template.area);
+    save_config(&config)?;
+    println!("✅ Area '{}' created from template '{}'.", name, template_name);
+    Ok(())
+}
+
+fn remove_area(name: &str, hard: bool) -> Result<()> {
+    let config = load_config()?;
+    if !config.areas.contains_key(name) {
+        return Err(anyhow!("Area '{}' not found.", name));
+    }
+
+    // Confirm deletion
+    if hard {
+        println!("WARNING: This will permanently delete all data for area '{}' (notes, flashcards, etc.).", name);
+    } else {
+        println!("This will remove area '{}' and move its data to the trash.", name);
+    }
+    print!("Type 'yes' to confirm: ");
+    io::stdout().flush()?;
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    if input.trim() != "yes" {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    let _lock = lock_data_dir()?;
+    auto_snapshot("remove-area")?;
+    // Reload in case another process changed the config while we were prompting
+    let mut config = load_config()?;
+
+    let area_config = config.areas.shift_remove(name).unwrap_or_default();
+    let area_path = area_dir(name);
+    let trashed = if hard {
+        if area_path.exists() {
+            fs::remove_dir_all(&area_path)?;
+        }
+        None
+    } else {
+        let paths: Vec<PathBuf> = area_path.exists().then(|| area_path.clone()).into_iter().collect();
+        Some(move_to_trash(&format!("remove-area {}", name), &paths, Some((name, area_config)))?)
+    };
+    save_config(&config)?;
+
+    // If current area was removed, switch to first available or clear
+    if let Some(current) = read_current_area()? {
+        if current == name {
+            if let Some(first) = config.areas.keys().next() {
                 write_current_area(first)?;
                 println!("Switched to '{}'.", first);
             } else {
@@ -628,7 +5342,120 @@ fn remove_area(name: &str) -> Result<()> {
         }
     }
 
-    println!("🗑️  Area '{}' removed.", name);
+    println!("🗑️  Area '{}' removed.", name);
+    if let Some(id) = trashed {
+        println!("Undo with `iceland restore {}`.", id);
+    }
+    Ok(())
+}
+
+fn rename_area(old: &str, new: &str) -> Result<()> {
+    validate_area_name(new)?;
+    let _lock = lock_data_dir()?;
+    let config = load_config()?;
+    if !config.areas.contains_key(old) {
+        return Err(anyhow!("Area '{}' not found.", old));
+    }
+    if config.areas.contains_key(new) || area_dir(new).exists() {
+        return Err(anyhow!("Area '{}' already exists.", new));
+    }
+
+    // Check sessions first so a broken sessions.csv stops us before anything moves
+    let mut sessions = load_sessions_for_edit()?;
+
+    if area_dir(old).exists() {
+        fs::rename(area_dir(old), area_dir(new))?;
+    }
+
+    // Keep the area's position in the list
+    let areas = config
+        .areas
+        .into_iter()
+        .map(|(name, area)| if name == old { (new.to_string(), area) } else { (name, area) })
+        .collect();
+    save_config(&Config { areas, ..config })?;
+
+    let mut renamed = 0;
+    for session in sessions.iter_mut().filter(|s| s.area == old) {
+        session.area = new.to_string();
+        renamed += 1;
+    }
+    if renamed > 0 {
+        save_sessions(&sessions)?;
+    }
+
+    if read_current_area()?.as_deref() == Some(old) {
+        write_current_area(new)?;
+    }
+    if let Some(mut running) = read_running_session()? {
+        if running.area == old {
+            running.area = new.to_string();
+            write_running_session(&running)?;
+        }
+    }
+    // So `close_apps` still finds what was launched before the rename
+    let mut launched = load_launched_processes()?;
+    if launched.processes.iter().any(|p| p.area == old) {
+        for process in launched.processes.iter_mut().filter(|p| p.area == old) {
+            process.area = new.to_string();
+        }
+        save_launched_processes(&launched)?;
+    }
+
+    println!("✏️  Area '{}' renamed to '{}' ({} sessions updated).", old, new, renamed);
+    Ok(())
+}
+
+fn clone_area(src: &str, dst: &str, with_content: bool) -> Result<()> {
+    validate_area_name(dst)?;
+    let _lock = lock_data_dir()?;
+    let mut config = load_config()?;
+    if !config.areas.contains_key(src) || !area_exists(src) {
+        return Err(anyhow!("Area '{}' not found.", src));
+    }
+    if config.areas.contains_key(dst) || area_dir(dst).exists() {
+        return Err(anyhow!("Area '{}' already exists.", dst));
+    }
+
+    if with_content {
+        copy_dir_recursive(&area_dir(src), &area_dir(dst), &[])?;
+    } else {
+        copy_area_skeleton(&area_dir(src), &area_dir(dst))?;
+    }
+
+    let area_config = AreaConfig {
+        archived: false,
+        ..config.area(src)
+    };
+    config.areas.insert(dst.to_string(), area_config);
+    save_config(&config)?;
+    println!("✅ Area '{}' cloned from '{}'.", dst, src);
+    Ok(())
+}
+
+fn set_area_archived(name: &str, archived: bool) -> Result<()> {
+    let _lock = lock_data_dir()?;
+    let mut config = load_config()?;
+    let area = config
+        .areas
+        .get_mut(name)
+        .ok_or_else(|| anyhow!("Area '{}' not found.", name))?;
+    if area.archived == archived {
+        let state = if archived { "already archived" } else { "not archived" };
+        println!("Area '{}' is {}.", name, state);
+        return Ok(());
+    }
+    if archived && read_running_session()?.is_some_and(|r| r.area == name) {
+        return Err(anyhow!("A session is running in '{}'. Stop it first.", name));
+    }
+    area.archived = archived;
+    save_config(&config)?;
+
+    if archived {
+        println!("📦 Area '{}' archived. Its data and history are kept.", name);
+    } else {
+        println!("📤 Area '{}' restored.", name);
+    }
     Ok(())
 }
 
@@ -640,37 +5467,14 @@ fn remove_area(name: &str) -> Result<()> {
 // HISTORY
 // ==============================================
 
-fn show_history(area_filter: Option<String>) -> Result<()> {
+fn show_history(area_filter: Option<String>, show_ids: bool) -> Result<()> {
     let file = sessions_file();
     if !file.exists() {
         println!("No sessions recorded.");
         return Ok(());
     }
 
-    let mut rdr = csv::Reader::from_path(file)?;
-    let mut sessions = Vec::new();
-    
-    // Manually read each record instead of deserializing
-    for result in rdr.records() {
-        let record = result?;
-        if record.len() >= 3 {
-            let area = record[0].to_string();
-            let start_str = record[1].to_string();
-            let end_str = record[2].to_string();
-            
-            // Parse the datetime strings
-            if let (Ok(start), Ok(end)) = (
-                DateTime::parse_from_rfc3339(&start_str),
-                DateTime::parse_from_rfc3339(&end_str)
-            ) {
-                sessions.push(Session {
-                    area,
-                    start: start.with_timezone(&Local),
-                    end: end.with_timezone(&Local),
-                });
-            }
-        }
-    }
+    let mut sessions = load_sessions()?;
 
     // Filter by area if requested
     if let Some(area) = area_filter {
@@ -683,10 +5487,15 @@ fn show_history(area_filter: Option<String>) -> Result<()> {
     }
 
     // Sort by start time (most recent first)
-    sessions.sort_by(|a, b| b.start.cmp(&a.start));
+    sessions.sort_by_key(|s| Reverse(s.start));
 
-    println!("\n{:<12} | {:<20} | {:<20} | Duration", "Area", "Start", "End");
-    println!("{:-<12}-+-{:-<20}-+-{:-<20}-+-{:-<8}", "", "", "", "");
+    let id_header = if show_ids { format!("{:>5} | ", "ID") } else { String::new() };
+    let id_rule = if show_ids { format!("{:-<5}-+-", "") } else { String::new() };
+    println!(
+        "\n{}{:<12} | {:<20} | {:<20} | Duration | Planned  | Tag",
+        id_header, "Area", "Start", "End"
+    );
+    println!("{}{:-<12}-+-{:-<20}-+-{:-<20}-+-{:-<8}-+-{:-<8}-+-{:-<8}", id_rule, "", "", "", "", "", "");
 
     for s in sessions {
         let start = s.start.format("%Y-%m-%d %H:%M");
@@ -695,29 +5504,246 @@ fn show_history(area_filter: Option<String>) -> Result<()> {
         let hours = duration.num_hours();
         let minutes = duration.num_minutes() % 60;
         
+        let planned = s
+            .planned
+            .map(|p| format_duration(Duration::seconds(p)))
+            .unwrap_or_default();
+
+        let id = if show_ids { format!("{:>5} | ", s.id) } else { String::new() };
+
         println!(
-            "{:<12} | {:>20} | {:>20} | {:2}:{:02}",
-            s.area, start, end, hours, minutes
+            "{}{:<12} | {:>20} | {:>20} | {:5}:{:02} | {:>8} | {}",
+            id, s.area, start, end, hours, minutes, planned, s.tag.as_deref().unwrap_or("")
         );
     }
     println!();
     Ok(())
 }
 
+// ==============================================
+// TEMPLATES
+// ==============================================
+
+/// Copy a directory tree into `dest`, skipping top-level entries named in `skip`.
+fn copy_dir_recursive(src: &Path, dest: &Path, skip: &[&str]) -> Result<()> {
+    fs::create_dir_all(dest)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if skip.iter().any(|s| name == **s) {
+            continue;
+        }
+        let path = entry.path();
+        if path.is_dir() {
+            copy_dir_recursive(&path, &dest.join(&name), &[])?;
+        } else {
+            fs::copy(&path, dest.join(&name))?;
+        }
+    }
+    Ok(())
+}
+
+fn builtin_template(name: &str) -> Option<&'static str> {
+    BUILTIN_TEMPLATES.iter().find(|(n, _)| *n == name).map(|(_, t)| *t)
+}
+
+/// Template names become file names under `templates/`, so no paths or dotfiles.
+fn validate_template_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+        return Err(anyhow!("'{}' cannot be used as a template name.", name));
+    }
+    Ok(())
+}
+
+/// Where a user template lives, if it exists: its directory or its manifest.
+fn user_template_path(name: &str) -> Option<PathBuf> {
+    validate_template_name(name).ok()?;
+    let dir = templates_dir().join(name);
+    let manifest = templates_dir().join(format!("{}.toml", name));
+    [dir, manifest].into_iter().find(|p| p.exists())
+}
+
+fn template_exists(name: &str) -> bool {
+    user_template_path(name).is_some() || builtin_template(name).is_some()
+}
+
+/// Areas use the template named after them if there is one.
+fn default_template_for(area: &str) -> String {
+    if template_exists(area) {
+        area.to_string()
+    } else {
+        DEFAULT_TEMPLATE.to_string()
+    }
+}
+
+/// Load a template, preferring user templates over built-in ones.
+fn load_template(name: &str) -> Result<Template> {
+    if let Some(path) = user_template_path(name) {
+        if path.is_dir() {
+            let manifest = path.join(TEMPLATE_MANIFEST);
+            let mut template: Template = if manifest.exists() {
+                toml::from_str(&fs::read_to_string(&manifest)?)
+                    .with_context(|| format!("Invalid template manifest {}", manifest.display()))?
+            } else {
+                Template::default()
+            };
+            template.source_dir = Some(path);
+            return Ok(template);
+        }
+        return toml::from_str(&fs::read_to_string(&path)?)
+            .with_context(|| format!("Invalid template manifest {}", path.display()));
+    }
+
+    let builtin = builtin_template(name)
+        .ok_or_else(|| anyhow!("Template '{}' not found. See `template list`.", name))?;
+    Ok(toml::from_str(builtin)?)
+}
+
+fn list_templates() -> Result<()> {
+    let mut names: Vec<String> = vec![];
+    if templates_dir().is_dir() {
+        for entry in fs::read_dir(templates_dir())? {
+            let path = entry?.path();
+            let name = if path.is_dir() {
+                path.file_name().and_then(|n| n.to_str())
+            } else if path.extension().is_some_and(|e| e == "toml") {
+                path.file_stem().and_then(|n| n.to_str())
+            } else {
+                None
+            };
+            if let Some(name) = name {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names.sort();
+    names.dedup();
+    for (name, _) in BUILTIN_TEMPLATES {
+        if !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    }
+
+    println!("Available templates:");
+    for name in &names {
+        let source = if user_template_path(name).is_some() { "" } else { " (built-in)" };
+        let description = load_template(name)?.description.unwrap_or_default();
+        println!("  {:<12} {}{}", name, description, source);
+    }
+    Ok(())
+}
+
+fn show_template(name: &str) -> Result<()> {
+    let template = load_template(name)?;
+    match user_template_path(name) {
+        Some(path) => println!("📐 Template '{}' ({})", name, path.display()),
+        None => println!("📐 Template '{}' (built-in)", name),
+    }
+
+    if let Some(source) = &template.source_dir {
+        println!("\nCopies:");
+        let mut entries: Vec<_> = fs::read_dir(source)?
+            .filter_map(|e| e.ok())
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .filter(|n| n != TEMPLATE_MANIFEST)
+            .collect();
+        entries.sort();
+        for entry in entries {
+            println!("  {}", entry);
+        }
+    }
+
+    let manifest = toml::to_string_pretty(&template)?;
+    if !manifest.trim().is_empty() {
+        println!("\n{}", manifest.trim_end());
+    }
+    Ok(())
+}
+
+/// Copy an area's top-level directories (empty), links.txt and flashcard decks
+/// without their review state. Notes and browser data stay behind.
+fn copy_area_skeleton(area_path: &Path, dest: &Path) -> Result<()> {
+    fs::create_dir_all(dest)?;
+    for entry in fs::read_dir(area_path)? {
+        let path = entry?.path();
+        let Some(file_name) = path.file_name() else { continue };
+        if path.is_dir() {
+            fs::create_dir_all(dest.join(file_name))?;
+        } else if file_name == "links.txt" {
+            fs::copy(&path, dest.join(file_name))?;
+        }
+    }
+
+    let decks = area_path.join("flashcards");
+    if decks.is_dir() {
+        for entry in fs::read_dir(&decks)? {
+            let path = entry?.path();
+            let hidden = path.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with('.'));
+            if path.is_file() && !hidden {
+                if let Some(file_name) = path.file_name() {
+                    fs::copy(&path, dest.join("flashcards").join(file_name))?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Save an area's directory skeleton, links and flashcard decks as a directory template.
+fn create_template_from(area: &str, name: Option<String>) -> Result<()> {
+    let area_path = area_dir(area);
+    if !area_path.is_dir() {
+        return Err(anyhow!("Area '{}' does not exist.", area));
+    }
+    let name = name.unwrap_or_else(|| area.to_string());
+    validate_template_name(&name)?;
+    if user_template_path(&name).is_some() {
+        return Err(anyhow!("Template '{}' already exists.", name));
+    }
+
+    let _lock = lock_data_dir()?;
+    let dest = templates_dir().join(&name);
+    copy_area_skeleton(&area_path, &dest)?;
+
+    let template = Template {
+        description: Some(format!("Created from area '{}'", area)),
+        area: load_config()?.area(area),
+        ..Template::default()
+    };
+    fs::write(dest.join(TEMPLATE_MANIFEST), toml::to_string_pretty(&template)?)?;
+
+    println!("📐 Template '{}' created in {}", name, dest.display());
+    Ok(())
+}
+
+fn manage_templates(action: TemplateAction) -> Result<()> {
+    match action {
+        TemplateAction::List => list_templates(),
+        TemplateAction::Show { name } => show_template(&name),
+        TemplateAction::CreateFrom { area, name } => create_template_from(&area, name),
+    }
+}
+
 // ==============================================
 // TUI SELECTOR
 // ==============================================
 
 fn tui_select_area() -> Result<()> {
     let config = load_config()?;
+    let names: Vec<_> = config
+        .areas
+        .iter()
+        .filter(|(_, a)| !a.archived)
+        .map(|(name, _)| name.clone())
+        .collect();
     let selection = Select::with_theme(&ColorfulTheme::default())
         .with_prompt("Select an area")
-        .items(&config.areas)
+        .items(&names)
         .default(0)
         .interact()?;
 
-    let chosen = &config.areas[selection];
-    switch_area(chosen)
+    let chosen = &names[selection];
+    switch_area(chosen, true)
 }
 
 // ==============================================
@@ -726,20 +5752,628 @@ fn tui_select_area() -> Result<()> {
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    let paths = resolve_data_paths(cli.home, cli.profile)?;
+    DATA_PATHS.get_or_init(|| paths);
+    run_migrations()?;
+    if let Err(e) = close_expired_session() {
+        eprintln!("Warning: could not close timeboxed session: {}", e);
+    }
     match cli.command {
         Commands::Init => init_iceland(),
-        Commands::List => list_areas(),
-        Commands::Switch { area } => switch_area(&area),
+        Commands::List { all } => list_areas(all),
+        Commands::Switch { area, no_launch } => switch_area(&area, !no_launch),
         Commands::Tui => tui_select_area(),
         Commands::Status => show_status(),
-        Commands::Stats => show_stats(),
-        Commands::Start => start_session(),
-        Commands::Stop => stop_session(),
-        Commands::Destroy { area, target } => destroy_in_area(&area, target),
-        Commands::Notes { area, text } => add_note(&area, &text),
+        Commands::Stats {
+            since,
+            until,
+            today,
+            week,
+            month,
+            group_by,
+            format,
+        } => show_stats(stats_range(since, until, today, week, month), group_by, format),
+        Commands::Start { tag, note, duration } => start_session(tag, note, duration).map(|_| ()),
+        Commands::Pomodoro {
+            work,
+            short_break,
+            long_break,
+            cycles,
+            rounds,
+        } => run_pomodoro(work, short_break, long_break, cycles, rounds),
+        Commands::Stop { at, ago } => stop_session(at.or(ago.map(|d| Local::now() - d))),
+        Commands::Destroy { area, target, hard } => destroy_in_area(&area, target, hard),
+        Commands::Notes { action, area, text } => match (action, area, text) {
+            (Some(action), _, _) => manage_notes(action),
+            (None, Some(area), text) => add_note(&area, text, None, &[]),
+            (None, None, _) => Err(anyhow!("Usage: iceland notes <area> [\"text\"|-] (or `notes add|list|show|edit`)")),
+        },
+        Commands::Journal {
+            action,
+            area,
+            text,
+            date,
+        } => match action {
+            Some(JournalAction::List { area }) => list_journal(area.as_deref()),
+            None => journal(area, text, date.unwrap_or_else(|| Local::now().date_naive())),
+        },
+        Commands::Capture { text, area, tags } => capture(text, area, &tags),
+        Commands::Inbox { action } => match action {
+            Some(InboxAction::Triage) => triage_inbox(),
+            None => list_inbox(),
+        },
+        Commands::Search {
+            query,
+            area,
+            scopes,
+            regex,
+            limit,
+        } => search(&query, area.as_deref(), &scopes, regex, limit),
         Commands::Flashcards { area } => tui_flashcards(&area),
-        Commands::AddArea { name } => add_area(&name),
-        Commands::RemoveArea { name } => remove_area(&name),
-        Commands::History { area } => show_history(area),
+        Commands::AddArea { name, template } => add_area(&name, template),
+        Commands::Template { action } => manage_templates(action),
+        Commands::RemoveArea { name, hard } => remove_area(&name, hard),
+        Commands::RenameArea { old, new } => rename_area(&old, &new),
+        Commands::CloneArea {
+            src,
+            dst,
+            with_content,
+        } => clone_area(&src, &dst, with_content),
+        Commands::ArchiveArea { name } => set_area_archived(&name, true),
+        Commands::UnarchiveArea { name } => set_area_archived(&name, false),
+        Commands::History { area } => show_history(area, false),
+        Commands::Log {
+            area,
+            start,
+            end,
+            duration,
+            date,
+            tag,
+            note,
+        } => log_session(&area, start, end, duration, date, tag, note),
+        Commands::Session { action } => manage_sessions(action),
+        Commands::Trash { action } => manage_trash(action),
+        Commands::Doctor { fix } => run_doctor(fix),
+        Commands::Backup { out } => backup(out),
+        Commands::Restore { target, area, dry_run } => {
+            if Path::new(&target).is_file() {
+                restore_backup(Path::new(&target), area.as_deref(), dry_run)
+            } else if area.is_some() || dry_run {
+                Err(anyhow!("'{}' is not a backup archive; --area and --dry-run only apply to archives.", target))
+            } else {
+                restore_from_trash(&target)
+            }
+        }
+    }
+}
+
+// ==============================================
+// TESTS
+// ==============================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "area,start,end,tag,note,planned,id\n";
+    const ROW: &str = "work,2026-10-16T08:00:00+00:00,2026-10-16T09:00:00+00:00,,,,1\n";
+
+    fn at(text: &str) -> DateTime<Local> {
+        let time = chrono::NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap();
+        Local.from_local_datetime(&time).unwrap()
+    }
+
+    fn session(id: u64, area: &str, start: &str, end: &str) -> Session {
+        Session {
+            area: area.to_string(),
+            start: at(start),
+            end: at(end),
+            tag: None,
+            note: None,
+            planned: None,
+            id,
+        }
+    }
+
+    /// Point the data dir at a temp dir shared by every test of this run.
+    fn test_home() -> PathBuf {
+        DATA_PATHS.get_or_init(|| {
+            let dir = std::env::temp_dir().join(format!("iceland-test-{}", std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            DataPaths {
+                data: dir.clone(),
+                config: dir,
+                profile: None,
+            }
+        });
+        iceland_dir()
+    }
+
+    #[test]
+    fn card_review_schedule() {
+        let today = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        let mut card = CardState::new("front", today);
+        card.review(Grade::Good, today);
+        assert_eq!((card.repetitions, card.interval), (1, 1));
+        card.review(Grade::Good, today);
+        assert_eq!((card.repetitions, card.interval), (2, 6));
+        card.review(Grade::Good, today);
+        assert_eq!(card.interval, (6.0 * card.ease).round() as i64);
+        assert_eq!(card.due, today + Duration::days(card.interval));
+    }
+
+    #[test]
+    fn card_review_again_resets() {
+        let today = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        let mut card = CardState::new("front", today);
+        card.review(Grade::Easy, today);
+        card.review(Grade::Easy, today);
+        assert!(card.ease > DEFAULT_EASE);
+
+        for _ in 0..10 {
+            card.review(Grade::Again, today);
+        }
+        assert_eq!((card.repetitions, card.interval), (0, 1));
+        assert_eq!(card.ease, MIN_EASE);
+    }
+
+    #[test]
+    fn card_due_when_unseen_or_scheduled() {
+        let today = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        let mut states = HashMap::new();
+        assert!(is_card_due(&states, "new card", today));
+
+        let mut card = CardState::new("seen", today);
+        card.review(Grade::Good, today);
+        states.insert(card.front.clone(), card);
+        assert!(!is_card_due(&states, "seen", today));
+        assert!(is_card_due(&states, "seen", today + Duration::days(1)));
+    }
+
+    #[test]
+    fn stats_by_area_sorted_by_time() {
+        let sessions = [
+            session(1, "math", "2026-03-02 08:00", "2026-03-02 09:00"),
+            session(2, "work", "2026-03-02 09:00", "2026-03-02 10:30"),
+            session(3, "work", "2026-03-03 09:00", "2026-03-03 09:30"),
+        ];
+        let rows = stat_rows(&sessions, (None, None), StatsGroup::Area);
+        let groups: Vec<_> = rows.iter().map(|r| (r.group.as_str(), r.seconds)).collect();
+        assert_eq!(groups, [("work", 7200), ("math", 3600)]);
+        assert!((rows[0].percent - 200.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn stats_by_period_within_range() {
+        let sessions = [
+            session(1, "work", "2026-03-01 10:00", "2026-03-01 11:00"), // Sunday, week 9
+            session(2, "work", "2026-03-02 10:00", "2026-03-02 12:00"), // Monday, week 10
+            session(3, "math", "2026-03-04 10:00", "2026-03-04 10:30"),
+        ];
+        let rows = stat_rows(&sessions, (None, None), StatsGroup::Week);
+        let groups: Vec<_> = rows.iter().map(|r| (r.group.as_str(), r.seconds)).collect();
+        assert_eq!(groups, [("2026-W09", 3600), ("2026-W10", 9000)]);
+
+        // Bounds are inclusive days
+        let day = |d| NaiveDate::from_ymd_opt(2026, 3, d);
+        let rows = stat_rows(&sessions, (day(2), day(2)), StatsGroup::Day);
+        let groups: Vec<_> = rows.iter().map(|r| (r.group.as_str(), r.seconds)).collect();
+        assert_eq!(groups, [("2026-03-02", 7200)]);
+        assert!(stat_rows(&sessions, (day(5), None), StatsGroup::Month).is_empty());
+    }
+
+    #[test]
+    fn stats_range_flags() {
+        let today = Local::now().date_naive();
+        assert_eq!(stats_range(None, None, true, false, false), (Some(today), Some(today)));
+
+        let (monday, until) = stats_range(None, None, false, true, false);
+        let monday = monday.unwrap();
+        assert_eq!(monday.weekday(), chrono::Weekday::Mon);
+        assert!(monday <= today && today - monday < Duration::days(7));
+        assert_eq!(until, Some(today));
+
+        let (first, _) = stats_range(None, None, false, false, true);
+        assert_eq!(first.unwrap().day(), 1);
+
+        let since = NaiveDate::from_ymd_opt(2026, 1, 1);
+        assert_eq!(stats_range(since, None, false, false, false), (since, None));
+    }
+
+    #[test]
+    fn repair_keeps_intact_file() {
+        assert_eq!(repair_sessions_csv(&format!("{}{}", HEADER, ROW)), None);
+        assert_eq!(repair_sessions_csv(HEADER), None);
+    }
+
+    #[test]
+    fn repair_adds_missing_newline() {
+        let content = format!("{}{}", HEADER, ROW.trim_end());
+        assert_eq!(repair_sessions_csv(&content), Some(format!("{}{}", HEADER, ROW)));
+    }
+
+    #[test]
+    fn repair_drops_truncated_row() {
+        let content = format!("{}{}work,2026-10-17T08:00:00+00:00,2026-10-1", HEADER, ROW);
+        assert_eq!(repair_sessions_csv(&content), Some(format!("{}{}", HEADER, ROW)));
+    }
+
+    #[test]
+    fn repair_keeps_multi_line_note() {
+        let multi_line = "work,2026-10-16T10:00:00+00:00,2026-10-16T11:00:00+00:00,,\"line one\nline two\",,2\n";
+        let content = format!("{}{}{}", HEADER, ROW, multi_line);
+        assert_eq!(repair_sessions_csv(&content), None);
+
+        let logged = "work,2026-10-15T08:00:00+00:00,2026-10-15T08:10:00+00:00,,,,3\n";
+        let content = format!("{}{}{}", HEADER, multi_line, logged);
+        assert_eq!(repair_sessions_csv(&content), None);
+    }
+
+    #[test]
+    fn repair_keeps_complete_broken_row() {
+        // Left for `doctor`, e.g. by the v0 migration
+        let content = format!("{}{}work,garbage,2026-10-10T10:00:00+00:00\n", HEADER, ROW);
+        assert_eq!(repair_sessions_csv(&content), None);
+    }
+
+    #[test]
+    fn repair_drops_row_cut_inside_quotes() {
+        let cut = "work,2026-10-16T10:00:00+00:00,2026-10-16T11:00:00+00:00,,\"line one\nline";
+        let content = format!("{}{}{}", HEADER, ROW, cut);
+        assert_eq!(repair_sessions_csv(&content), Some(format!("{}{}", HEADER, ROW)));
+    }
+
+    #[test]
+    fn parse_duration_units() {
+        assert_eq!(parse_duration("25").unwrap(), Duration::minutes(25));
+        assert_eq!(parse_duration("25m").unwrap(), Duration::minutes(25));
+        assert_eq!(parse_duration("1h30m").unwrap(), Duration::minutes(90));
+        assert_eq!(parse_duration(" 90s ").unwrap(), Duration::seconds(90));
+        assert_eq!(parse_duration("2d").unwrap(), Duration::days(2));
+    }
+
+    #[test]
+    fn parse_duration_rejects_garbage() {
+        for input in ["", "m", "1x", "1h30", "0m", "abc", "0", "-5"] {
+            assert!(parse_duration(input).is_err(), "{:?} should be rejected", input);
+        }
+    }
+
+    #[test]
+    fn timebox_deadline() {
+        let running = RunningSession {
+            planned: Some(25 * 60),
+            ..RunningSession::new("work")
+        };
+        assert_eq!(running.deadline(), Some(running.start + Duration::minutes(25)));
+        assert_eq!(RunningSession::new("work").deadline(), None);
+    }
+
+    #[test]
+    fn validate_session_rejects_bad_times_and_overlaps() {
+        let sessions = [
+            session(1, "work", "2026-03-02 09:00", "2026-03-02 10:00"),
+            session(2, "math", "2026-03-02 11:00", "2026-03-02 12:00"),
+        ];
+        // Touching another session and keeping its own slot are fine
+        assert!(validate_session(&sessions, &session(3, "work", "2026-03-02 10:00", "2026-03-02 11:00")).is_ok());
+        assert!(validate_session(&sessions, &session(1, "work", "2026-03-02 08:30", "2026-03-02 10:00")).is_ok());
+
+        assert!(validate_session(&sessions, &session(3, "work", "2026-03-02 10:00", "2026-03-02 10:00")).is_err());
+        assert!(validate_session(&sessions, &session(3, "work", "2026-03-02 09:30", "2026-03-02 10:30")).is_err());
+        assert!(validate_session(&sessions, &session(1, "work", "2026-03-02 09:00", "2026-03-02 11:30")).is_err());
+        let future = Session { end: Local::now() + Duration::hours(1), ..sessions[0].clone() };
+        assert!(validate_session(&sessions, &future).is_err());
+    }
+
+    #[test]
+    fn split_session_on_its_own_day() {
+        let mut sessions = vec![
+            session(1, "work", "2026-03-02 10:00", "2026-03-02 11:00"),
+            session(4, "work", "2026-03-01 23:00", "2026-03-02 01:00"),
+        ];
+        assert_eq!(split_session(&mut sessions, 1, "10:20").unwrap(), 5);
+        assert_eq!((sessions[0].end, sessions[1].start), (at("2026-03-02 10:20"), at("2026-03-02 10:20")));
+        assert_eq!(sessions[1].end, at("2026-03-02 11:00"));
+
+        // Past midnight, a bare time falls on the day the session ended
+        let second = split_session(&mut sessions, 4, "00:30").unwrap();
+        let i = session_index(&sessions, second).unwrap();
+        assert_eq!(sessions[i].start, at("2026-03-02 00:30"));
+
+        assert!(split_session(&mut sessions, 1, "12:00").is_err());
+        assert!(split_session(&mut sessions, 9, "10:30").is_err());
+    }
+
+    #[test]
+    fn merge_sessions_keeps_earlier_one() {
+        let mut sessions = vec![
+            session(1, "work", "2026-03-02 09:00", "2026-03-02 10:00"),
+            session(2, "work", "2026-03-02 10:30", "2026-03-02 11:00"),
+            session(3, "math", "2026-03-02 11:30", "2026-03-02 12:00"),
+            session(4, "work", "2026-03-02 12:30", "2026-03-02 13:00"),
+        ];
+        assert_eq!(merge_sessions(&mut sessions, 2, 1).unwrap(), (1, 2));
+        assert_eq!(sessions.len(), 3);
+        assert_eq!((sessions[0].start, sessions[0].end), (at("2026-03-02 09:00"), at("2026-03-02 11:00")));
+
+        assert!(merge_sessions(&mut sessions, 1, 1).is_err());
+        assert!(merge_sessions(&mut sessions, 1, 3).is_err()); // different areas
+        assert!(merge_sessions(&mut sessions, 1, 4).is_err()); // would swallow session 3
+    }
+
+    #[test]
+    fn parse_time_on_day() {
+        let day = NaiveDate::from_ymd_opt(2026, 3, 10).unwrap();
+        let time = parse_time_on("09:15", Some(day)).unwrap();
+        assert_eq!(time.naive_local(), day.and_hms_opt(9, 15, 0).unwrap());
+
+        // An explicit date wins over the default day
+        let time = parse_time_on("2026-03-01 18:00", Some(day)).unwrap();
+        assert_eq!(time.naive_local(), NaiveDate::from_ymd_opt(2026, 3, 1).unwrap().and_hms_opt(18, 0, 0).unwrap());
+
+        let time = parse_time_on("2026-03-01T18:00:00+00:00", Some(day)).unwrap();
+        assert_eq!(time, DateTime::parse_from_rfc3339("2026-03-01T18:00:00+00:00").unwrap());
+    }
+
+    #[test]
+    fn parse_time_relative_and_bare() {
+        let before = Local::now();
+        let time = parse_time_on("-2h", None).unwrap();
+        let expected = before - Duration::hours(2);
+        assert!((time - expected).num_seconds().abs() <= 1);
+
+        // A bare time is its most recent occurrence
+        let time = parse_time_on("23:59", None).unwrap();
+        assert!(time <= Local::now() && Local::now() - time < Duration::days(1));
+
+        assert!(parse_time_on("25:00", None).is_err());
+        assert!(parse_time_on("tomorrow 10:00", None).is_err());
+    }
+
+    #[test]
+    fn first_free_slot_skips_recorded_sessions() {
+        let day = NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+        assert_eq!(first_free_slot(&[], day, Duration::hours(1)).unwrap(), at("2026-03-02 09:00"));
+
+        let sessions = [
+            session(1, "work", "2026-03-02 09:30", "2026-03-02 10:00"),
+            session(2, "work", "2026-03-02 10:30", "2026-03-02 11:00"),
+        ];
+        assert_eq!(first_free_slot(&sessions, day, Duration::minutes(30)).unwrap(), at("2026-03-02 09:00"));
+        assert_eq!(first_free_slot(&sessions, day, Duration::minutes(45)).unwrap(), at("2026-03-02 11:00"));
+    }
+
+    #[test]
+    fn migrations_cover_every_version() {
+        let steps: Vec<_> = MIGRATIONS.iter().map(|m| m.from).collect();
+        assert_eq!(steps, (0..SCHEMA_VERSION).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn upgrade_sessions_csv_keeps_broken_rows() {
+        let broken = "work,garbage,2026-10-16T10:00:00+00:00";
+        let old = format!(
+            "area,start,end\n{}\n{}\n{}\n",
+            "work,2026-10-16T08:00:00+00:00,2026-10-16T09:00:00+00:00",
+            broken,
+            "math,2026-10-16T10:00:00+00:00,2026-10-16T11:00:00+00:00"
+        );
+        let upgraded = upgrade_sessions_csv(&old).unwrap();
+        let lines: Vec<_> = upgraded.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], SESSIONS_HEADER);
+        assert_eq!(lines[3], broken);
+
+        let sessions = parse_sessions_strict(&lines[..3].join("\n")).unwrap();
+        let ids: Vec<_> = sessions.iter().map(|s| (s.area.as_str(), s.id)).collect();
+        assert_eq!(ids, [("work", 1), ("math", 2)]);
+
+        // Nothing parseable still leaves a current header
+        let upgraded = upgrade_sessions_csv(&format!("area,start,end\n{}\n", broken)).unwrap();
+        assert_eq!(upgraded, format!("{}\n{}\n", SESSIONS_HEADER, broken));
+    }
+
+    #[test]
+    fn config_reads_flat_area_list() {
+        let config: Config = toml::from_str("areas = [\"work\", \"math\"]\n").unwrap();
+        assert_eq!(config.areas.keys().collect::<Vec<_>>(), ["work", "math"]);
+        assert_eq!(config.schema_version, 0);
+    }
+
+    #[test]
+    fn template_dest_stays_inside_area() {
+        let area = Path::new("/data/work");
+        assert_eq!(template_dest(area, "notes/a.md").unwrap(), area.join("notes/a.md"));
+        for bad in ["", "../x", "notes/../../x", "/etc/passwd", "./a"] {
+            assert!(template_dest(area, bad).is_err(), "{:?} should be rejected", bad);
+        }
+    }
+
+    #[test]
+    fn trash_round_trip() {
+        let notes = test_home().join("trash-test").join("notes");
+        fs::create_dir_all(&notes).unwrap();
+        fs::write(notes.join("a.md"), "keep me").unwrap();
+
+        let id = move_to_trash("destroy trash-test notes", std::slice::from_ref(&notes), None).unwrap();
+        assert!(!notes.exists());
+        let trash = load_trash().unwrap();
+        let (_, manifest) = trash.iter().find(|(entry, _)| *entry == id).unwrap();
+        assert_eq!(manifest.items, [PathBuf::from("trash-test/notes")]);
+
+        // Something new in the way is never overwritten; an empty dir is
+        fs::create_dir_all(&notes).unwrap();
+        fs::write(notes.join("b.md"), "new").unwrap();
+        assert!(restore_from_trash(&id).is_err());
+        fs::remove_file(notes.join("b.md")).unwrap();
+
+        restore_from_trash(&id).unwrap();
+        assert_eq!(fs::read_to_string(notes.join("a.md")).unwrap(), "keep me");
+        assert!(!trash_entry_dir(&id).exists());
+        fs::remove_dir_all(test_home().join("trash-test")).unwrap();
+    }
+
+    /// A fresh directory for one test's files.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("iceland-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// A backup archive whose manifest lists `listed` but which holds `entries`.
+    fn write_test_archive(path: &Path, listed: &[(&str, &str)], entries: &[(&str, &str)]) {
+        let manifest = BackupManifest {
+            schema_version: SCHEMA_VERSION,
+            app_version: "test".to_string(),
+            created: Local::now(),
+            areas: vec!["work".to_string(), "math".to_string()],
+            files: listed
+                .iter()
+                .map(|(name, content)| (name.to_string(), sha256_hex(content.as_bytes())))
+                .collect(),
+        };
+        let manifest = toml::to_string_pretty(&manifest).unwrap();
+        let mut archive = tar::Builder::new(zstd::Encoder::new(File::create(path).unwrap(), 0).unwrap());
+        for (name, content) in [(BACKUP_MANIFEST, manifest.as_str())].iter().chain(entries) {
+            let mut header = tar::Header::new_gnu();
+            // Written raw, since `set_path` refuses the `..` some tests need
+            header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            archive.append(&header, content.as_bytes()).unwrap();
+        }
+        archive.into_inner().unwrap().finish().unwrap();
+    }
+
+    #[test]
+    fn extract_backup_stages_one_area() {
+        let dir = scratch_dir("backup-area");
+        let archive = dir.join("backup.tar.zst");
+        let files = [
+            ("config.toml", "snapshots = 0\n"),
+            ("data/work/notes/a.md", "hello"),
+            ("data/math/b.md", "other area"),
+        ];
+        write_test_archive(&archive, &files, &files);
+        assert_eq!(read_backup_manifest(&archive).unwrap().areas, ["work", "math"]);
+
+        let staging = dir.join("staging");
+        let extracted = extract_backup(&archive, Some("work"), Some(&staging)).unwrap();
+        assert_eq!(extracted.config.as_deref(), Some("snapshots = 0\n"));
+        assert_eq!(extracted.files, [PathBuf::from("work/notes/a.md")]);
+        assert_eq!(fs::read_to_string(staging.join("work/notes/a.md")).unwrap(), "hello");
+        assert!(!staging.join("math").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn extract_backup_rejects_damaged_archives() {
+        let dir = scratch_dir("backup-damaged");
+        let archive = dir.join("backup.tar.zst");
+        let check = |listed: &[(&str, &str)], entries: &[(&str, &str)], error: &str| {
+            write_test_archive(&archive, listed, entries);
+            let message = extract_backup(&archive, None, None).unwrap_err().to_string();
+            assert!(message.contains(error), "{:?} should mention {:?}", message, error);
+        };
+        check(&[("data/a.md", "hello")], &[("data/a.md", "hellO")], "Checksum mismatch");
+        check(&[], &[("data/a.md", "hello")], "not listed");
+        check(&[("data/a.md", "a"), ("data/b.md", "b")], &[("data/a.md", "a")], "incomplete");
+
+        // Nothing may land outside the staging dir
+        let escape = [("data/../escape.md", "x")];
+        write_test_archive(&archive, &escape, &escape);
+        let staging = dir.join("staging");
+        let message = extract_backup(&archive, None, Some(&staging)).unwrap_err().to_string();
+        assert!(message.contains("unsafe path"), "{}", message);
+        assert!(!dir.join("escape.md").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn note_round_trip() {
+        let created = Local.with_ymd_and_hms(2026, 5, 4, 10, 30, 0).unwrap();
+        let note = Note {
+            id: "20260504-103000".to_string(),
+            title: Some("Quotes \"and\": colons".to_string()),
+            area: "work".to_string(),
+            tags: vec!["rust".to_string(), "db".to_string()],
+            created,
+            updated: created + Duration::minutes(5),
+            body: "First line\n\n---\nafter a rule".to_string(),
+            path: PathBuf::from("/data/work/notes/20260504-103000.md"),
+        };
+        let parsed = parse_note("work", &note.path, &render_note(&note)).unwrap();
+        assert_eq!(parsed.id, note.id);
+        assert_eq!(parsed.title, note.title);
+        assert_eq!(parsed.tags, note.tags);
+        assert_eq!(parsed.created, note.created);
+        assert_eq!(parsed.updated, note.updated);
+        assert_eq!(parsed.body, note.body);
+    }
+
+    #[test]
+    fn parse_note_needs_front_matter() {
+        let path = Path::new("README.md");
+        assert!(parse_note("work", path, "# Just Markdown\n").is_none());
+        assert!(parse_note("work", path, "---\ntitle: x\n---\n").is_none());
+    }
+
+    #[test]
+    fn parse_hand_written_note() {
+        let content = "---\r\ntitle: Plain title\r\ntags: [a, 'b c']\r\ncreated: 2026-05-04T10:30:00+02:00\r\n---\r\n\r\nBody\r\n";
+        let note = parse_note("work", Path::new("notes/my-note.md"), content).unwrap();
+        assert_eq!(note.id, "my-note");
+        assert_eq!(note.title.as_deref(), Some("Plain title"));
+        assert_eq!(note.tags, ["a", "b c"]);
+        assert_eq!(note.updated, note.created);
+        assert_eq!(note.body, "Body");
+    }
+
+    #[test]
+    fn score_line_ranks_words_case_and_headings() {
+        let pattern = RegexBuilder::new("rust").case_insensitive(true).build().unwrap();
+        let line = |text: &str, heading| IndexedLine {
+            line: 1,
+            text: text.to_string(),
+            heading,
+        };
+        assert_eq!(score_line(&pattern, "rust", &line("nothing here", true)), 0);
+        assert_eq!(score_line(&pattern, "rust", &line("TRUSTY", false)), 2);
+        assert_eq!(score_line(&pattern, "rust", &line("Rust", false)), 5);
+        assert_eq!(score_line(&pattern, "rust", &line("rust", false)), 6);
+        assert_eq!(score_line(&pattern, "rust", &line("rust and rust", false)), 12);
+        assert_eq!(score_line(&pattern, "rust", &line("# rust", true)), 11);
+    }
+
+    #[test]
+    fn snippet_windows_around_first_match() {
+        let pattern = RegexBuilder::new("needle").case_insensitive(true).build().unwrap();
+        let plain = |text: &str, width| console::strip_ansi_codes(&snippet(&pattern, text, width)).into_owned();
+        assert_eq!(plain("  a needle here  ", 40), "a needle here");
+
+        let long = format!("{}needle{}", "é".repeat(30), "x".repeat(30));
+        let cut = plain(&long, 18);
+        assert_eq!(cut, format!("…{}needle{}…", "é".repeat(6), "x".repeat(6)));
+    }
+
+    #[test]
+    fn journal_summary_splices_between_markers() {
+        let summary = format!("{}\nnew\n{}", JOURNAL_SUMMARY_START, JOURNAL_SUMMARY_END);
+        let existing = format!(
+            "# work\n\n{}\nold\n{}\n\n### 09:00\n\nmy words\n",
+            JOURNAL_SUMMARY_START, JOURNAL_SUMMARY_END
+        );
+        assert_eq!(
+            splice_journal_summary(&existing, &summary),
+            format!("# work\n\n{}\n\n### 09:00\n\nmy words\n", summary)
+        );
+
+        // Markers removed or out of order: nothing written by hand is lost
+        for existing in ["my words\n".to_string(), format!("{}\n{}\n", JOURNAL_SUMMARY_END, JOURNAL_SUMMARY_START)] {
+            assert_eq!(splice_journal_summary(&existing, &summary), format!("{}\n\n{}", summary, existing));
+        }
     }
 }